@@ -0,0 +1,77 @@
+use crate::error::DuelError;
+
+/// A little-endian cursor over one of the buffers handed back by ocgcore.
+///
+/// Every read is bounds checked, so a malformed or truncated buffer
+/// surfaces as a [`DuelError::BufferUnderflow`] instead of a panic.
+#[derive(Debug, Clone)]
+pub(crate) struct BufferReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+macro_rules! read_le {
+    ($($name:ident => $ty:ty),* $(,)?) => {
+        $(
+            pub fn $name(&mut self) -> Result<$ty, DuelError> {
+                let bytes = self.read_bytes(std::mem::size_of::<$ty>())?;
+                Ok(<$ty>::from_le_bytes(bytes.try_into().unwrap()))
+            }
+        )*
+    };
+}
+
+impl<'a> BufferReader<'a> {
+    pub fn new(buf: &'a [u8]) -> BufferReader<'a> {
+        BufferReader { buf, pos: 0 }
+    }
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+    /// Consumes and returns everything left in the buffer.
+    pub fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.buf[self.pos..];
+        self.pos = self.buf.len();
+        rest
+    }
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DuelError> {
+        if self.remaining() < len {
+            return Err(DuelError::BufferUnderflow {
+                offset: self.pos,
+                needed: len - self.remaining(),
+            });
+        }
+        let bytes = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+    pub fn skip(&mut self, len: usize) -> Result<(), DuelError> {
+        self.read_bytes(len).map(|_| ())
+    }
+    read_le! {
+        read_u8 => u8,
+        read_u16 => u16,
+        read_u32 => u32,
+        read_i32 => i32,
+        read_u64 => u64,
+    }
+    pub fn read_bool(&mut self) -> Result<bool, DuelError> {
+        Ok(self.read_u8()? != 0)
+    }
+    /// Reads `count` items with `read`.
+    pub fn read_vec<T>(
+        &mut self,
+        count: usize,
+        mut read: impl FnMut(&mut Self) -> Result<T, DuelError>,
+    ) -> Result<Vec<T>, DuelError> {
+        // Cap the preallocation so a corrupted count can't make us allocate gigabytes.
+        let mut vec = Vec::with_capacity(count.min(self.remaining()));
+        for _ in 0..count {
+            vec.push(read(self)?);
+        }
+        Ok(vec)
+    }
+}
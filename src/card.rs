@@ -3,7 +3,7 @@ use std::mem::forget;
 
 use crate::ffi::{OCG_CardData, OCG_NewCardInfo};
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct NewCardInfo {
     pub team: u8,
     pub duelist: u8,
@@ -0,0 +1,321 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::card::CardData;
+use crate::common::TYPE_LINK;
+use crate::duel::CardHandler;
+use crate::error::DuelError;
+
+/// Card data loaded from the `datas` table of one or more EDOPro `.cdb` files.
+///
+/// Every row is unpacked into a [`CardData`] when a file is loaded,
+/// so lookups from the card handler never touch the database.
+#[derive(Debug, Clone, Default)]
+pub struct CardDatabase {
+    cards: HashMap<u32, CardData>,
+}
+
+impl CardDatabase {
+    pub fn new() -> CardDatabase {
+        CardDatabase::default()
+    }
+    /// Opens a single `.cdb` file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<CardDatabase, DuelError> {
+        let mut database = CardDatabase::new();
+        database.load(path)?;
+        Ok(database)
+    }
+    /// Opens every `.cdb` file in order, later files overriding earlier ones by code.
+    pub fn open_all<P, I>(paths: I) -> Result<CardDatabase, DuelError>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = P>,
+    {
+        let mut database = CardDatabase::new();
+        for path in paths {
+            database.load(path)?;
+        }
+        Ok(database)
+    }
+    /// Loads a `.cdb` file into this database, overriding cards that were already loaded.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), DuelError> {
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        self.load_connection(&connection)
+    }
+    fn load_connection(&mut self, connection: &Connection) -> Result<(), DuelError> {
+        let mut statement = connection.prepare(
+            "SELECT id, alias, setcode, type, atk, def, level, race, attribute FROM datas",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(unpack_card_data(
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+                row.get(6)?,
+                row.get(7)?,
+                row.get(8)?,
+            ))
+        })?;
+        for card in rows {
+            let card = card?;
+            self.cards.insert(card.code, card);
+        }
+        Ok(())
+    }
+    pub fn get(&self, code: u32) -> Option<&CardData> {
+        self.cards.get(&code)
+    }
+    pub fn insert(&mut self, card: CardData) -> Option<CardData> {
+        self.cards.insert(card.code, card)
+    }
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+    /// Returns the card with the provided code,
+    /// or an empty card with that code if it is not in the database.
+    pub fn card_data(&self, code: u32) -> CardData {
+        self.get(code).cloned().unwrap_or_else(|| CardData {
+            code,
+            ..Default::default()
+        })
+    }
+    /// Converts the database into a handler for [`DuelBuilder::set_card_handler`](crate::DuelBuilder::set_card_handler).
+    pub fn into_card_handler(self) -> impl CardHandler {
+        move |code| self.card_data(code)
+    }
+}
+
+/// The strings of one row of the `texts` table.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CardText {
+    pub name: String,
+    pub desc: String,
+    /// The `str1` to `str16` columns, referred to by effect descriptions.
+    pub strings: [String; 16],
+}
+
+/// Card names and effect strings loaded from the `texts` table of one or more EDOPro `.cdb` files.
+#[derive(Debug, Clone, Default)]
+pub struct CardTexts {
+    texts: HashMap<u32, CardText>,
+}
+
+impl CardTexts {
+    pub fn new() -> CardTexts {
+        CardTexts::default()
+    }
+    /// Opens a single `.cdb` file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<CardTexts, DuelError> {
+        let mut texts = CardTexts::new();
+        texts.load(path)?;
+        Ok(texts)
+    }
+    /// Opens every `.cdb` file in order, later files overriding earlier ones by code.
+    pub fn open_all<P, I>(paths: I) -> Result<CardTexts, DuelError>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = P>,
+    {
+        let mut texts = CardTexts::new();
+        for path in paths {
+            texts.load(path)?;
+        }
+        Ok(texts)
+    }
+    /// Loads a `.cdb` file into these texts, overriding cards that were already loaded.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), DuelError> {
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        self.load_connection(&connection)
+    }
+    fn load_connection(&mut self, connection: &Connection) -> Result<(), DuelError> {
+        let columns = (1..=16).map(|i| format!(", str{i}")).collect::<String>();
+        let mut statement =
+            connection.prepare(&format!("SELECT id, name, desc{columns} FROM texts"))?;
+        let rows = statement.query_map([], |row| {
+            // Unofficial databases sometimes leave unused strings NULL instead of empty.
+            let text = |index: usize| -> rusqlite::Result<String> {
+                Ok(row.get::<_, Option<String>>(index)?.unwrap_or_default())
+            };
+            let mut strings: [String; 16] = Default::default();
+            for (i, string) in strings.iter_mut().enumerate() {
+                *string = text(i + 3)?;
+            }
+            Ok((
+                row.get::<_, i64>(0)? as u32,
+                CardText {
+                    name: text(1)?,
+                    desc: text(2)?,
+                    strings,
+                },
+            ))
+        })?;
+        for row in rows {
+            let (code, text) = row?;
+            self.texts.insert(code, text);
+        }
+        Ok(())
+    }
+    pub fn get(&self, code: u32) -> Option<&CardText> {
+        self.texts.get(&code)
+    }
+    pub fn insert(&mut self, code: u32, text: CardText) -> Option<CardText> {
+        self.texts.insert(code, text)
+    }
+    pub fn len(&self) -> usize {
+        self.texts.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.texts.is_empty()
+    }
+    pub fn name(&self, code: u32) -> Option<&str> {
+        self.get(code).map(|text| text.name.as_str())
+    }
+    /// Resolves an effect description, as found in messages like [`Message::Hint`](crate::Message::Hint)
+    /// or [`ActivatableEffect::desc`](crate::ActivatableEffect::desc), to the string it refers to.
+    ///
+    /// Returns [`None`] for system strings (descriptions below `1 << 20`),
+    /// unknown cards and empty strings.
+    pub fn description(&self, desc: u64) -> Option<&str> {
+        let (code, index) = split_description(desc)?;
+        let string = self.get(code)?.strings.get(index as usize)?;
+        (!string.is_empty()).then_some(string.as_str())
+    }
+    /// Describes an effect description for logs,
+    /// falling back to the card name and string index when the string itself is unknown.
+    pub fn describe(&self, desc: u64) -> String {
+        if let Some(string) = self.description(desc) {
+            return string.to_owned();
+        }
+        match split_description(desc) {
+            Some((code, index)) => match self.name(code) {
+                Some(name) => format!("{name} effect #{}", index + 1),
+                None => format!("{code} effect #{}", index + 1),
+            },
+            None => format!("system string {desc}"),
+        }
+    }
+}
+
+/// Splits an effect description into the card code and the index of the `strN` column it refers to,
+/// or [`None`] if it refers to a system string.
+pub fn split_description(desc: u64) -> Option<(u32, u32)> {
+    let code = (desc >> 20) as u32;
+    (code != 0).then_some((code, (desc & 0xfffff) as u32))
+}
+
+/// Unpacks the columns of a `datas` row the same way EDOPro does.
+#[allow(clippy::too_many_arguments)]
+fn unpack_card_data(
+    code: i64,
+    alias: i64,
+    setcode: i64,
+    card_type: i64,
+    attack: i64,
+    defense: i64,
+    level: i64,
+    race: i64,
+    attribute: i64,
+) -> CardData {
+    // Up to four 16 bit setcodes are packed into the setcode column.
+    let setcodes: HashSet<u16> = (0..4)
+        .map(|i| ((setcode as u64) >> (i * 16)) as u16)
+        .filter(|&setcode| setcode != 0)
+        .collect();
+    let card_type = card_type as u32;
+    let level = level as u32;
+    let (defense, link_marker) = if card_type & TYPE_LINK != 0 {
+        // Link monsters store their link markers in place of their defense.
+        (0, defense as u32)
+    } else {
+        (defense as i32, 0)
+    };
+    CardData {
+        code: code as u32,
+        alias: alias as u32,
+        setcodes,
+        card_type,
+        level: level & 0xff,
+        attribute: attribute as u32,
+        race: race as u64,
+        attack: attack as i32,
+        defense,
+        lscale: (level >> 24) & 0xff,
+        rscale: (level >> 16) & 0xff,
+        link_marker,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::*;
+
+    fn test_connection() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE datas (id INTEGER PRIMARY KEY, ot INTEGER, alias INTEGER,
+                    setcode INTEGER, type INTEGER, atk INTEGER, def INTEGER, level INTEGER,
+                    race INTEGER, attribute INTEGER, category INTEGER);
+                INSERT INTO datas VALUES (70095154, 3, 0, 0x1093, 33, 2100, 1600, 5, 32, 16, 0);
+                INSERT INTO datas VALUES (14558127, 3, 0, 0x10ec00ec, 16777249, 1500, 1100,
+                    0x08080004, 8192, 32, 0);
+                INSERT INTO datas VALUES (1861629, 3, 0, 0x101, 67108897, 2300, 261, 4,
+                    16777216, 32, 0);
+                CREATE TABLE texts (id INTEGER PRIMARY KEY, name TEXT, desc TEXT,
+                    str1 TEXT, str2 TEXT, str3 TEXT, str4 TEXT, str5 TEXT, str6 TEXT,
+                    str7 TEXT, str8 TEXT, str9 TEXT, str10 TEXT, str11 TEXT, str12 TEXT,
+                    str13 TEXT, str14 TEXT, str15 TEXT, str16 TEXT);
+                INSERT INTO texts (id, name, desc, str1, str2) VALUES
+                    (55144522, 'Pot of Greed', 'Draw 2 cards.', 'Draw 2 cards', '');",
+            )
+            .unwrap();
+        connection
+    }
+
+    #[test]
+    fn test_load_card_database() {
+        let mut database = CardDatabase::new();
+        database.load_connection(&test_connection()).unwrap();
+        assert_eq!(database.len(), 3);
+        let cyber_dragon = database.get(70095154).unwrap();
+        assert_eq!(cyber_dragon.setcodes, HashSet::from([0x1093]));
+        assert_eq!(cyber_dragon.level, 5);
+        assert_eq!(cyber_dragon.defense, 1600);
+        let pendulum = database.get(14558127).unwrap();
+        assert_eq!(pendulum.setcodes, HashSet::from([0xec, 0x10ec]));
+        assert_eq!(pendulum.level, 4);
+        assert_eq!(pendulum.lscale, 8);
+        assert_eq!(pendulum.rscale, 8);
+        let link = database.get(1861629).unwrap();
+        assert_eq!(link.defense, 0);
+        assert_eq!(
+            link.link_marker,
+            LINK_MARKER_BOTTOM_LEFT | LINK_MARKER_BOTTOM_RIGHT | LINK_MARKER_TOP_RIGHT
+        );
+        assert_eq!(database.card_data(1).code, 1);
+    }
+    #[test]
+    fn test_load_card_texts() {
+        let mut texts = CardTexts::new();
+        texts.load_connection(&test_connection()).unwrap();
+        assert_eq!(texts.len(), 1);
+        assert_eq!(texts.name(55144522), Some("Pot of Greed"));
+        assert_eq!(texts.description(55144522 << 20), Some("Draw 2 cards"));
+        assert_eq!(texts.description((55144522 << 20) | 1), None);
+        assert_eq!(
+            texts.describe((55144522 << 20) | 1),
+            "Pot of Greed effect #2"
+        );
+        assert_eq!(texts.describe(1160), "system string 1160");
+        assert_eq!(split_description(1160), None);
+    }
+}
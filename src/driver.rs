@@ -0,0 +1,180 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+
+use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures_channel::oneshot;
+use futures_core::Stream;
+
+use crate::duel::{panic_message, Duel, DuelStatus};
+use crate::error::DuelError;
+use crate::message::{Message, Messages};
+use crate::response::Response;
+use crate::worker::{DuelWorker, SendDuel};
+
+type MessageSender = UnboundedSender<Result<Message, DuelError>>;
+
+/// What the worker thread knows about the prompt the duel is waiting on.
+#[derive(Debug, Default)]
+struct DriverState {
+    awaiting: Option<u8>,
+}
+
+/// Drives a duel on a [`DuelWorker`] thread from async code.
+///
+/// The duel is processed until it needs a response,
+/// and every message produced along the way is sent to [`messages`](#method.messages).
+/// [`respond`](#method.respond) resumes processing until the next prompt or the end of the duel,
+/// at which point the message stream ends.
+///
+/// This only depends on `futures` channels, so it works with any executor.
+#[derive(Debug)]
+pub struct AsyncDuel {
+    worker: DuelWorker,
+    state: Arc<Mutex<DriverState>>,
+    sender: MessageSender,
+    receiver: UnboundedReceiver<Result<Message, DuelError>>,
+}
+
+impl AsyncDuel {
+    /// Starts the duel, which should already have its cards, and begins processing it.
+    pub fn start(duel: SendDuel) -> AsyncDuel {
+        let (sender, receiver) = unbounded();
+        let duel = AsyncDuel {
+            worker: DuelWorker::new(duel),
+            state: Arc::default(),
+            sender,
+            receiver,
+        };
+        let (state, sender) = (duel.state.clone(), duel.sender.clone());
+        duel.worker.submit(move |duel| {
+            let result = drive(&sender, || {
+                duel.start();
+                advance(duel, &mut state.lock().unwrap(), &sender);
+                Ok(())
+            });
+            if let Err(err) = result {
+                let _ = sender.unbounded_send(Err(err));
+            }
+        });
+        duel
+    }
+    /// Returns the stream of decoded messages.
+    /// It pauses while the duel waits for a response and ends with the duel.
+    pub fn messages(&mut self) -> impl Stream<Item = Result<Message, DuelError>> + '_ {
+        &mut self.receiver
+    }
+    /// Responds to the pending prompt and processes the duel until the next one.
+    ///
+    /// Returns [`DuelError::NotAwaitingResponse`] if no prompt is pending,
+    /// [`DuelError::UnexpectedResponder`] if the prompt is for the other player
+    /// and [`DuelError::WorkerStopped`] if the worker thread stopped without answering.
+    /// Messages are sent to [`messages`](#method.messages) before this resolves.
+    pub async fn respond(&self, player: u8, response: Response) -> Result<(), DuelError> {
+        let (result_sender, result) = oneshot::channel();
+        let (state, sender) = (self.state.clone(), self.sender.clone());
+        self.worker.submit(move |duel| {
+            let result = drive(&sender, || {
+                let mut state = state.lock().unwrap();
+                match state.awaiting {
+                    None => return Err(DuelError::NotAwaitingResponse),
+                    Some(expected) if expected != player => {
+                        return Err(DuelError::UnexpectedResponder { expected, player })
+                    }
+                    Some(_) => (),
+                }
+                duel.respond(&response);
+                advance(duel, &mut state, &sender);
+                Ok(())
+            });
+            let _ = result_sender.send(result);
+        });
+        result.await.unwrap_or(Err(DuelError::WorkerStopped))
+    }
+}
+
+/// Runs `f`, turning a panic into an error and ending the message stream.
+fn drive(
+    sender: &MessageSender,
+    f: impl FnOnce() -> Result<(), DuelError>,
+) -> Result<(), DuelError> {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        sender.close_channel();
+        Err(DuelError::HandlerPanic(panic_message(&*panic)))
+    })
+}
+
+/// Processes the duel until it awaits a response or ends, sending every message.
+fn advance(duel: &Duel, state: &mut DriverState, sender: &MessageSender) {
+    // The engine does not repeat a prompt after MSG_RETRY, so the same player answers again.
+    let previous = state.awaiting.take();
+    loop {
+        let status = duel.process();
+        let buffer = duel.get_message();
+        for message in Messages::new(&buffer) {
+            match &message {
+                Ok(Message::Retry) => state.awaiting = previous,
+                Ok(message) => {
+                    if let Some(player) = message.prompt_player() {
+                        state.awaiting = Some(player);
+                    }
+                }
+                Err(_) => (),
+            }
+            let _ = sender.unbounded_send(message);
+        }
+        match status {
+            DuelStatus::Continue => (),
+            DuelStatus::Awaiting => return,
+            DuelStatus::End => {
+                state.awaiting = None;
+                sender.close_channel();
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::response::IdleCmdAction;
+    use crate::worker::SendDuelBuilder;
+
+    /// Waits for the next prompt, or returns [`None`] once the stream ended.
+    fn next_prompt(duel: &mut AsyncDuel) -> Option<Message> {
+        block_on(async {
+            while let Some(message) = duel.messages().next().await {
+                let message = message.unwrap();
+                if message.prompt_player().is_some() {
+                    return Some(message);
+                }
+            }
+            None
+        })
+    }
+
+    #[test]
+    fn test_respond_to_prompt() {
+        let mut duel = AsyncDuel::start(SendDuelBuilder::new().build());
+        let prompt = next_prompt(&mut duel).expect("the duel ended without a prompt");
+        let player = prompt.prompt_player().unwrap();
+        let Message::SelectIdleCmd(_) = prompt else {
+            panic!("unexpected prompt {prompt:?}");
+        };
+        assert!(matches!(
+            block_on(duel.respond(1 - player, Response::SelectIdleCmd(IdleCmdAction::EndPhase))),
+            Err(DuelError::UnexpectedResponder { expected, player: responder })
+                if expected == player && responder == 1 - player
+        ));
+        block_on(duel.respond(player, Response::SelectIdleCmd(IdleCmdAction::EndPhase))).unwrap();
+        // Without cards, the next player cannot draw and loses, which ends the stream.
+        assert_eq!(next_prompt(&mut duel), None);
+        assert!(matches!(
+            block_on(duel.respond(player, Response::SelectIdleCmd(IdleCmdAction::EndPhase))),
+            Err(DuelError::NotAwaitingResponse)
+        ));
+    }
+}
@@ -1,6 +1,11 @@
+use std::any::Any;
 use std::ffi::{CStr, CString};
+use std::marker::PhantomPinned;
 use std::os::raw::c_void;
-use std::ptr::null_mut;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::ptr::{addr_of_mut, null_mut};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use crate::ffi::{
     OCG_CardData, OCG_CreateDuel, OCG_DestroyDuel,
@@ -17,7 +22,12 @@ use crate::ffi::{
 
 use crate::card::{CardData, NewCardInfo};
 use crate::error::DuelError;
+use crate::field::FieldSnapshot;
+use crate::logging::LogType;
 use crate::player::Player;
+use crate::query::CardQuery;
+use crate::response::Response;
+use crate::snapshot::{DuelSnapshot, SNAPSHOT_LOCATIONS, SNAPSHOT_QUERY_FLAGS};
 use crate::QueryInfo;
 
 pub trait CardHandler: FnMut(u32) -> CardData + 'static {}
@@ -30,19 +40,30 @@ impl<T: FnMut(&Duel, &str) -> Result<(), Box<dyn std::error::Error>> + 'static>
     for T
 {
 }
-trait ScriptHandlerWrapper: FnMut(*mut c_void, &str) -> i32 + 'static {}
-impl<T: FnMut(*mut c_void, &str) -> i32 + 'static> ScriptHandlerWrapper for T {}
-pub trait LogHandler: FnMut(&str, i32) + 'static {}
-impl<T: FnMut(&str, i32) + 'static> LogHandler for T {}
+trait ScriptHandlerWrapper:
+    FnMut(*mut c_void, &str) -> Result<(), Box<dyn std::error::Error>> + 'static
+{
+}
+impl<T: FnMut(*mut c_void, &str) -> Result<(), Box<dyn std::error::Error>> + 'static>
+    ScriptHandlerWrapper for T
+{
+}
+pub trait LogHandler: FnMut(&str, LogType) + 'static {}
+impl<T: FnMut(&str, LogType) + 'static> LogHandler for T {}
 pub trait CardReadDoneHandler: FnMut(&CardData) + 'static {}
 impl<T: FnMut(&CardData) + 'static> CardReadDoneHandler for T {}
 
+/// The scripts EDOPro loads into every duel, in order.
+/// `utility.lua` loads the `proc_*.lua` summon procedure scripts itself.
+pub const EDOPRO_STARTUP_SCRIPTS: [&str; 2] = ["constant.lua", "utility.lua"];
+
 pub struct DuelBuilder {
     card_handler: Box<dyn CardHandler>,
     script_handler: Box<dyn ScriptHandler>,
     script_handler_wrapper: Box<dyn ScriptHandlerWrapper>,
     log_handler: Box<dyn LogHandler>,
     card_read_done_handler: Box<dyn CardReadDoneHandler>,
+    startup_scripts: Vec<String>,
     seed: [u64; 4],
     flags: u64,
     team_1: Player,
@@ -58,9 +79,10 @@ impl Default for DuelBuilder {
                 ..Default::default()
             }),
             script_handler: Box::new(|_, _| Err("No script handler set!")?),
-            script_handler_wrapper: Box::new(|_, _| 0),
+            script_handler_wrapper: Box::new(|_, _| Ok(())),
             log_handler: Box::new(|_, _| ()),
             card_read_done_handler: Box::new(|_| ()),
+            startup_scripts: Vec::new(),
             seed: [0; 4],
             flags: 0,
             team_1: Player::default(),
@@ -95,6 +117,7 @@ impl DuelBuilder {
     /// Sets the log handler for the duel.
     /// By default, the handler is no-op.
     /// You most likely want to set this to something that will use your logging framework of choice.
+    /// See [`log_handler`](crate::logging) and [`tracing_handler`](crate::logging) behind the `log` and `tracing` features.
     pub fn set_log_handler<F>(&mut self, callback: F)
     where
         F: LogHandler,
@@ -115,9 +138,16 @@ impl DuelBuilder {
     {
         self.card_read_done_handler = Box::new(callback);
     }
+    // The trampolines below must never unwind into the engine,
+    // so panics are caught and kept in the payload until the duel call that triggered them returns.
     extern "C" fn card_handler_ffi(cb: *mut c_void, code: u32, data: *mut OCG_CardData) {
-        let closure = unsafe { &mut *(cb as *mut Box<dyn CardHandler>) };
-        let card_data: CardData = closure(code);
+        let payload = unsafe { &mut *(cb as *mut Payload<dyn CardHandler>) };
+        let card_data = payload
+            .call(|handler| handler(code))
+            .unwrap_or_else(|| CardData {
+                code,
+                ..Default::default()
+            });
         unsafe { data.write(card_data.into_ocg_carddata_leaky()) };
     }
     extern "C" fn script_handler_ffi(
@@ -125,17 +155,19 @@ impl DuelBuilder {
         duel_ptr: *mut c_void,
         name: *const i8,
     ) -> i32 {
-        let name_str = unsafe { CStr::from_ptr(name) };
-        let closure = unsafe { &mut *(cb as *mut Box<dyn ScriptHandlerWrapper>) };
-        closure(duel_ptr, name_str.to_str().unwrap())
+        let name_str = unsafe { CStr::from_ptr(name) }.to_string_lossy();
+        let payload = unsafe { &mut *(cb as *mut Payload<dyn ScriptHandlerWrapper>) };
+        payload
+            .call(|handler| handler(duel_ptr, &name_str).is_ok())
+            .unwrap_or(false) as i32
     }
     extern "C" fn log_handler_ffi(cb: *mut c_void, msg: *const i8, msg_type: i32) {
-        let msg_str = unsafe { CStr::from_ptr(msg) };
-        let closure = unsafe { &mut *(cb as *mut Box<dyn LogHandler>) };
-        closure(msg_str.to_str().unwrap(), msg_type)
+        let msg_str = unsafe { CStr::from_ptr(msg) }.to_string_lossy();
+        let payload = unsafe { &mut *(cb as *mut Payload<dyn LogHandler>) };
+        payload.call(|handler| handler(&msg_str, msg_type.into()));
     }
     extern "C" fn card_read_done_handler_ffi(cb: *mut c_void, data: *mut OCG_CardData) {
-        let closure = unsafe { &mut *(cb as *mut Box<dyn CardReadDoneHandler>) };
+        let payload = unsafe { &mut *(cb as *mut Payload<dyn CardReadDoneHandler>) };
         let card_data: CardData = unsafe { data.read().into() };
         // deallocate setcodes once it has been copied to card_data
         let setcode_ptr = unsafe { data.read().setcodes };
@@ -156,7 +188,24 @@ impl DuelBuilder {
             // But we can assume it is, as the vector capacity is constructed with the length of the array in card.rs.
             drop(unsafe { Vec::from_raw_parts(setcode_ptr, len, len) });
         }
-        closure(&card_data)
+        payload.call(|handler| handler(&card_data));
+    }
+    /// Adds a script that is loaded through the script handler right after the duel is created.
+    /// Startup scripts are loaded in the order they were added.
+    pub fn add_startup_script(&mut self, name: &str) {
+        self.startup_scripts.push(name.to_owned());
+    }
+    /// Replaces the startup scripts, see [`add_startup_script`](#method.add_startup_script).
+    pub fn set_startup_scripts<S, I>(&mut self, names: I)
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = S>,
+    {
+        self.startup_scripts = names.into_iter().map(Into::into).collect();
+    }
+    /// Loads the same global scripts as EDOPro on startup, see [`EDOPRO_STARTUP_SCRIPTS`].
+    pub fn use_edopro_startup_scripts(&mut self) {
+        self.set_startup_scripts(EDOPRO_STARTUP_SCRIPTS);
     }
     pub fn set_seed(&mut self, seed: [u64; 4]) {
         self.seed = seed;
@@ -173,64 +222,115 @@ impl DuelBuilder {
     pub fn set_enable_unsafe_libraries(&mut self, enable: bool) {
         self.enable_unsafe_libraries = enable;
     }
-    pub fn build(mut self) -> Duel {
-        let mut duel = Duel { ptr: null_mut() };
-        // This needs to be done so that the script_handler is able to access the duel instance
-        // We can assume that the duel instance will be valid for the lifetime of the script_handler,
-        // as this is only called on OCGDuelInstance::new_card and OCG_CreateDuel.
-        // Because this is called on OCG_CreateDuel, we need to set the pointer here,
-        // so that the instance will have the correct pointer when OCG_CreateDuel is called.
-        // `duel` should never go out of scope during construction.
-        // If it does, this would become a memory leak, as the pointer would be lost before it is properly set in OCG_CreateDuel.
-        let mut_ptr = &mut duel as *mut Duel;
-        self.script_handler_wrapper = Box::new(move |duel_ptr, name| {
-            if duel_ptr != mut_ptr as *mut _ {
-                unsafe {
-                    let mut_ptr_mut = mut_ptr.as_mut().unwrap();
-                    mut_ptr_mut.ptr = duel_ptr;
-                    return (self.script_handler)(mut_ptr_mut, name).is_ok() as i32;
-                }
-            }
-            unsafe {
-                let mut_ptr_ref = mut_ptr.as_ref().unwrap();
-                (self.script_handler)(mut_ptr_ref, name).is_ok() as i32
+    pub fn seed(&self) -> [u64; 4] {
+        self.seed
+    }
+    pub fn flags(&self) -> u64 {
+        self.flags
+    }
+    pub fn team_1(&self) -> Player {
+        self.team_1
+    }
+    pub fn team_2(&self) -> Player {
+        self.team_2
+    }
+    /// Creates the duel and loads the startup scripts.
+    ///
+    /// # Panics
+    /// Panics if [`try_build`](#method.try_build) fails.
+    pub fn build(self) -> Pin<Box<Duel>> {
+        self.try_build()
+            .unwrap_or_else(|err| panic!("Failed to create duel: {err}"))
+    }
+    /// Creates the duel and loads the startup scripts,
+    /// returning an error if the engine fails to create the duel or a startup script fails to load,
+    /// and [`DuelError::HandlerPanic`] if a handler panicked while the engine was creating the duel.
+    ///
+    /// The duel is pinned because the script handler keeps a pointer to it for as long as the duel lives.
+    pub fn try_build(mut self) -> Result<Pin<Box<Duel>>, DuelError> {
+        let panic = HandlerPanic::default();
+        let mut duel = Box::pin(Duel {
+            ptr: null_mut(),
+            handlers: DuelHandlers::default(),
+            panic: panic.clone(),
+            _pinned: PhantomPinned,
+        });
+        // The script handler is given this pointer as `&Duel` whenever the engine requests a script,
+        // which happens on OCG_CreateDuel, Duel::load_script and Duel::new_card.
+        // The duel is pinned on the heap, so the pointer stays valid until the duel is dropped,
+        // which also destroys the engine instance that could call the handler.
+        let duel_ptr = unsafe { duel.as_mut().get_unchecked_mut() as *mut Duel };
+        self.script_handler_wrapper = Box::new(move |ocg_duel, name| unsafe {
+            // The engine can request scripts before OCG_CreateDuel has written out the duel pointer.
+            if (*duel_ptr).ptr.is_null() {
+                (*duel_ptr).ptr = ocg_duel;
             }
+            (self.script_handler)(&*duel_ptr, name)
         });
         // Double indirection is required for the callback pointers
+        let handlers = DuelHandlers {
+            card_handler: Payload::into_raw(self.card_handler, &panic),
+            script_handler_wrapper: Payload::into_raw(self.script_handler_wrapper, &panic),
+            log_handler: Payload::into_raw(self.log_handler, &panic),
+            card_read_done_handler: Payload::into_raw(self.card_read_done_handler, &panic),
+        };
+        let script_handler_wrapper = handlers.script_handler_wrapper;
         let options = OCG_DuelOptions {
             cardReader: Some(Self::card_handler_ffi),
-            payload1: Box::into_raw(Box::new(self.card_handler)) as *mut _,
+            payload1: handlers.card_handler as *mut _,
             scriptReader: Some(Self::script_handler_ffi),
-            payload2: Box::into_raw(Box::new(self.script_handler_wrapper)) as *mut _,
+            payload2: handlers.script_handler_wrapper as *mut _,
             logHandler: Some(Self::log_handler_ffi),
-            payload3: Box::into_raw(Box::new(self.log_handler)) as *mut _,
+            payload3: handlers.log_handler as *mut _,
             cardReaderDone: Some(Self::card_read_done_handler_ffi),
-            payload4: Box::into_raw(Box::new(self.card_read_done_handler)) as *mut _,
+            payload4: handlers.card_read_done_handler as *mut _,
             seed: self.seed,
             flags: self.flags,
             team1: self.team_1.into(),
             team2: self.team_2.into(),
             enableUnsafeLibraries: self.enable_unsafe_libraries.into(),
         };
-        let res_code: i32 = unsafe { OCG_CreateDuel(&mut duel.ptr, options) };
+        // The duel owns the handlers from here on, so they are freed even if creation fails.
+        unsafe { (*duel_ptr).handlers = handlers };
+        let res_code: i32 = unsafe { OCG_CreateDuel(addr_of_mut!((*duel_ptr).ptr), options) };
+        if let Some(panic) = duel.panic.take() {
+            return Err(DuelError::HandlerPanic(panic_message(&*panic)));
+        }
         #[allow(non_upper_case_globals)]
         match res_code.try_into() {
-            Ok(OCG_DuelCreationStatus_OCG_DUEL_CREATION_SUCCESS) => duel,
-            // These should never happen if types are abided by, so we panic.
+            Ok(OCG_DuelCreationStatus_OCG_DUEL_CREATION_SUCCESS) => (),
             Ok(OCG_DuelCreationStatus_OCG_DUEL_CREATION_NO_OUTPUT) => {
-                panic!("Failed to create duel: OCG_DUEL_CREATION_NO_OUTPUT")
+                return Err(DuelError::CreationNoOutput)
             }
             Ok(OCG_DuelCreationStatus_OCG_DUEL_CREATION_NOT_CREATED) => {
-                panic!("Failed to create duel: OCG_DUEL_CREATION_NOT_CREATED")
+                return Err(DuelError::CreationNotCreated)
             }
             Ok(OCG_DuelCreationStatus_OCG_DUEL_CREATION_NULL_DATA_READER) => {
-                panic!("Failed to create duel: OCG_DUEL_CREATION_NULL_DATA_READER")
+                return Err(DuelError::CreationNullDataReader)
             }
             Ok(OCG_DuelCreationStatus_OCG_DUEL_CREATION_NULL_SCRIPT_READER) => {
-                panic!("Failed to create duel: OCG_DUEL_CREATION_NULL_SCRIPT_READER")
+                return Err(DuelError::CreationNullScriptReader)
+            }
+            _ => return Err(DuelError::CreationUnknown(res_code)),
+        }
+        for name in &self.startup_scripts {
+            // The script handler is called the same way the engine would call it.
+            let script_handler = unsafe { &mut (*script_handler_wrapper).handler };
+            if let Err(err) = script_handler(duel.ptr, name) {
+                // Errors of script handlers do not have to be thread safe, unlike `DuelError`,
+                // so anything but a `DuelError` is kept as its message.
+                let source: Box<dyn std::error::Error + Send + Sync> =
+                    match err.downcast::<DuelError>() {
+                        Ok(err) => err,
+                        Err(err) => err.to_string().into(),
+                    };
+                return Err(DuelError::StartupScriptFailure {
+                    name: name.clone(),
+                    source,
+                });
             }
-            _ => panic!("Failed to create duel to to unknown error"),
         }
+        Ok(duel)
     }
 }
 
@@ -264,19 +364,122 @@ impl From<OCG_DuelStatus> for DuelStatus {
 ///
 /// Construction of this struct MUST always handled by the DuelBuilder.
 /// Otherwise, memory leaks could happen!
+/// The builder returns the duel pinned, as the script handler refers to it by address.
 #[derive(Debug)]
 pub struct Duel {
     ptr: *mut c_void,
+    handlers: DuelHandlers,
+    panic: HandlerPanic,
+    _pinned: PhantomPinned,
 }
 
 impl Drop for Duel {
     fn drop(&mut self) {
+        // The handlers are dropped after this, once the engine can no longer call them.
         unsafe {
             OCG_DestroyDuel(self.ptr);
         }
     }
 }
 
+/// The first panic raised by a handler while the engine was calling it.
+///
+/// It is shared between the duel and its handler payloads,
+/// which a [`SendDuel`](crate::SendDuel) moves to another thread together.
+#[derive(Clone, Default)]
+struct HandlerPanic(Arc<Mutex<Option<Box<dyn Any + Send>>>>);
+
+impl HandlerPanic {
+    fn lock(&self) -> MutexGuard<'_, Option<Box<dyn Any + Send>>> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+    fn take(&self) -> Option<Box<dyn Any + Send>> {
+        self.lock().take()
+    }
+}
+
+impl std::fmt::Debug for HandlerPanic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandlerPanic").finish_non_exhaustive()
+    }
+}
+
+/// Returns the message a panic was raised with, if it was raised with one.
+pub(crate) fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_owned()
+    }
+}
+
+/// A handler handed to the engine as a callback payload.
+struct Payload<T: ?Sized> {
+    panic: HandlerPanic,
+    handler: Box<T>,
+}
+
+impl<T: ?Sized> Payload<T> {
+    fn into_raw(handler: Box<T>, panic: &HandlerPanic) -> *mut Payload<T> {
+        Box::into_raw(Box::new(Payload {
+            panic: panic.clone(),
+            handler,
+        }))
+    }
+    /// Calls the handler, catching a panic instead of letting it unwind into the engine.
+    /// Only the first panic is kept until the duel resumes it.
+    fn call<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        match catch_unwind(AssertUnwindSafe(|| f(&mut self.handler))) {
+            Ok(result) => Some(result),
+            Err(panic) => {
+                self.panic.lock().get_or_insert(panic);
+                None
+            }
+        }
+    }
+}
+
+/// The callback payloads handed to the engine by [`DuelBuilder`], freed when the [`Duel`] is dropped.
+#[derive(Debug)]
+struct DuelHandlers {
+    card_handler: *mut Payload<dyn CardHandler>,
+    script_handler_wrapper: *mut Payload<dyn ScriptHandlerWrapper>,
+    log_handler: *mut Payload<dyn LogHandler>,
+    card_read_done_handler: *mut Payload<dyn CardReadDoneHandler>,
+}
+
+impl Default for DuelHandlers {
+    fn default() -> DuelHandlers {
+        DuelHandlers {
+            card_handler: null_mut(),
+            script_handler_wrapper: null_mut(),
+            log_handler: null_mut(),
+            card_read_done_handler: null_mut(),
+        }
+    }
+}
+
+impl Drop for DuelHandlers {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.card_handler.is_null() {
+                drop(Box::from_raw(self.card_handler));
+            }
+            if !self.script_handler_wrapper.is_null() {
+                drop(Box::from_raw(self.script_handler_wrapper));
+            }
+            if !self.log_handler.is_null() {
+                drop(Box::from_raw(self.log_handler));
+            }
+            if !self.card_read_done_handler.is_null() {
+                drop(Box::from_raw(self.card_read_done_handler));
+            }
+        }
+    }
+}
+
 impl Duel {
     // Informative
     pub fn get_version() -> [i32; 2] {
@@ -301,10 +504,14 @@ impl Duel {
     // Lifecycle
     /// Add the card specified by info to the duel.
     /// This calls the provided card_handler info.code and script_handler if the card script has not been loaded yet.
+    ///
+    /// # Panics
+    /// Resumes the panic of a handler that panicked while the engine was calling it.
     pub fn new_card(&self, info: NewCardInfo) {
         unsafe {
             OCG_DuelNewCard(self.ptr, info.into());
         }
+        self.resume_handler_panic();
     }
     /// Start the duel simulation and state machine.
     /// Call this after all options and cards for the duel have been loaded.
@@ -312,17 +519,29 @@ impl Duel {
         unsafe {
             OCG_StartDuel(self.ptr);
         }
+        self.resume_handler_panic();
+    }
+    /// Resumes a panic raised by a handler while the engine was calling it.
+    fn resume_handler_panic(&self) {
+        if let Some(panic) = self.panic.take() {
+            resume_unwind(panic);
+        }
     }
     /// Simply calls drop on self.
     /// This will in turn call [`OCG_DestroyDuel`] on the internal pointer,
     /// deallocating the internal duel.
-    pub fn destroy(self) {
+    pub fn destroy(self: Pin<Box<Self>>) {
         drop(self);
     }
     // Processing
     /// Runs the state machine to start the duel or after a waiting state requiring a player response.
+    ///
+    /// # Panics
+    /// Resumes the panic of a handler that panicked while the engine was calling it.
     pub fn process(&self) -> DuelStatus {
-        unsafe { (OCG_DuelProcess(self.ptr) as OCG_DuelStatus).into() }
+        let status = unsafe { OCG_DuelProcess(self.ptr) as OCG_DuelStatus };
+        self.resume_handler_panic();
+        status.into()
     }
     /// The main interface to the simulation.
     /// Returns a copy of the internal buffer containing all messages from the duel simulation.
@@ -352,9 +571,16 @@ impl Duel {
             );
         }
     }
+    /// Serializes the response and sets it as the next player response.
+    /// See [`set_response`](#method.set_response).
+    pub fn respond(&self, response: &Response) {
+        self.set_response(&response.to_bytes());
+    }
     /// Load a Lua card script or supporting script for the specified duel.
     /// Generally you do not call this directly except to load global scripts;
     /// instead you want to call this from your handler provided to [`set_script_handler`](struct.DuelBuilder.html#method.set_script_handler).
+    ///
+    /// Returns [`DuelError::HandlerPanic`] if a handler panicked while the script was running.
     pub fn load_script(&self, src_code: &str, name: &str) -> Result<(), DuelError> {
         let src_code = CString::new(src_code)?;
         let name_cstr = CString::new(name)?;
@@ -366,6 +592,9 @@ impl Duel {
                 name_cstr.as_ptr(),
             )
         };
+        if let Some(panic) = self.panic.take() {
+            return Err(DuelError::HandlerPanic(panic_message(&*panic)));
+        }
         if result == 0 {
             return Err(DuelError::ScriptLoadFailure(name.to_owned()));
         }
@@ -432,13 +661,81 @@ impl Duel {
         }
         Some(result_vec)
     }
+    /// Queries the field with [`query_field`](#method.query_field) and decodes the result.
+    pub fn query_field_snapshot(&self) -> Result<Option<FieldSnapshot>, DuelError> {
+        self.query_field()
+            .map(|buffer| FieldSnapshot::parse(&buffer))
+            .transpose()
+    }
+    /// Returns everything the engine knows right now: the field, and every card of
+    /// both players queried from each of the [`SNAPSHOT_LOCATIONS`] with [`SNAPSHOT_QUERY_FLAGS`].
+    pub fn snapshot(&self) -> Result<DuelSnapshot, DuelError> {
+        self.snapshot_with_flags(SNAPSHOT_QUERY_FLAGS)
+    }
+    /// Returns a hash of the raw buffers of [`query_field`](#method.query_field)
+    /// and of the same location queries as [`snapshot`](#method.snapshot).
+    ///
+    /// Two duels in the same state hash equally on any platform and with any Rust version,
+    /// so the hash can be stored and compared later to detect desyncs.
+    pub fn state_hash(&self) -> u64 {
+        // 64 bit FNV-1a, as the std hashers are not guaranteed to be stable.
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut write = |buffer: Option<Vec<u8>>| {
+            let buffer = buffer.unwrap_or_default();
+            for byte in (buffer.len() as u32)
+                .to_le_bytes()
+                .into_iter()
+                .chain(buffer)
+            {
+                hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+            }
+        };
+        write(self.query_field());
+        for con in 0..2 {
+            for loc in SNAPSHOT_LOCATIONS {
+                write(self.query_location(QueryInfo {
+                    flags: SNAPSHOT_QUERY_FLAGS,
+                    con,
+                    loc,
+                    ..Default::default()
+                }));
+            }
+        }
+        hash
+    }
+    /// Same as [`snapshot`](#method.snapshot), but cards are queried with the provided `QUERY_*` flags.
+    pub fn snapshot_with_flags(&self, flags: u32) -> Result<DuelSnapshot, DuelError> {
+        let field = FieldSnapshot::parse(&self.query_field().unwrap_or_default())?;
+        let mut snapshot = DuelSnapshot {
+            flags,
+            field,
+            ..Default::default()
+        };
+        for (con, player) in snapshot.players.iter_mut().enumerate() {
+            for loc in SNAPSHOT_LOCATIONS {
+                let buffer = self.query_location(QueryInfo {
+                    flags,
+                    con: con as u8,
+                    loc,
+                    ..Default::default()
+                });
+                if let (Some(buffer), Some(cards)) = (buffer, player.location_mut(loc)) {
+                    *cards = CardQuery::parse_location(&buffer)?;
+                }
+            }
+        }
+        Ok(snapshot)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::cell::{Cell, RefCell};
     use std::collections::{hash_map::RandomState, HashSet};
+    use std::rc::Rc;
 
     use super::*;
+    use crate::common::*;
 
     #[test]
     fn test_get_version() {
@@ -496,6 +793,124 @@ mod tests {
             .is_ok());
     }
     #[test]
+    fn test_startup_scripts_duel() {
+        let loaded = Rc::new(RefCell::new(Vec::new()));
+        let mut duel_builder = DuelBuilder::default();
+        let loaded_clone = loaded.clone();
+        duel_builder.set_script_handler(move |_, name| {
+            loaded_clone.borrow_mut().push(name.to_owned());
+            Ok(())
+        });
+        duel_builder.use_edopro_startup_scripts();
+        duel_builder.add_startup_script("proc_skill.lua");
+        duel_builder.build();
+        assert_eq!(
+            *loaded.borrow(),
+            ["constant.lua", "utility.lua", "proc_skill.lua"]
+        );
+    }
+    #[test]
+    #[should_panic(expected = "`missing.lua`")]
+    fn test_startup_script_failure_duel() {
+        let mut duel_builder = DuelBuilder::default();
+        duel_builder.add_startup_script("missing.lua");
+        duel_builder.build();
+    }
+    #[test]
+    fn test_try_build_startup_script_failure() {
+        let mut duel_builder = DuelBuilder::default();
+        duel_builder.set_script_handler(|duel, name| Ok(duel.load_script("invalid script", name)?));
+        duel_builder.add_startup_script("invalid.lua");
+        match duel_builder.try_build() {
+            Err(DuelError::StartupScriptFailure { name, source }) => {
+                assert_eq!(name, "invalid.lua");
+                assert!(matches!(
+                    source.downcast_ref::<DuelError>(),
+                    Some(DuelError::ScriptLoadFailure(_))
+                ));
+            }
+            _ => panic!("expected a startup script failure"),
+        }
+    }
+    #[test]
+    fn test_script_handler_after_build() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut duel_builder = DuelBuilder::default();
+        let seen_clone = seen.clone();
+        duel_builder.set_script_handler(move |duel, name| {
+            seen_clone
+                .borrow_mut()
+                .push((duel as *const Duel as usize, name.to_owned()));
+            Ok(duel.load_script(include_str!("../assets/c511005093.lua"), name)?)
+        });
+        // Move the duel around after the builder has returned.
+        let mut duels = vec![duel_builder.build()];
+        let duel = duels.pop().unwrap();
+        duel.new_card(NewCardInfo {
+            team: 0,
+            duelist: 0,
+            code: 511005093,
+            con: 0,
+            loc: LOCATION_DECK,
+            seq: 0,
+            pos: POS_FACEDOWN_DEFENSE as u32,
+        });
+        let seen = seen.borrow();
+        assert!(seen.contains(&(&*duel as *const Duel as usize, "c511005093.lua".to_owned())));
+    }
+    #[test]
+    fn test_drop_duel_handlers() {
+        struct DropCounter(Rc<Cell<usize>>);
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+        let drops = Rc::new(Cell::new(0));
+        let mut duel_builder = DuelBuilder::default();
+        let counter = DropCounter(drops.clone());
+        duel_builder.set_card_handler(move |code| {
+            let _ = &counter;
+            CardData {
+                code,
+                ..Default::default()
+            }
+        });
+        let counter = DropCounter(drops.clone());
+        duel_builder.set_script_handler(move |_, _| {
+            let _ = &counter;
+            Ok(())
+        });
+        let counter = DropCounter(drops.clone());
+        duel_builder.set_log_handler(move |_, _| {
+            let _ = &counter;
+        });
+        let counter = DropCounter(drops.clone());
+        duel_builder.set_card_read_done_handler(move |_| {
+            let _ = &counter;
+        });
+        let duel = duel_builder.build();
+        assert_eq!(drops.get(), 0);
+        drop(duel);
+        assert_eq!(drops.get(), 4);
+    }
+    #[test]
+    fn test_handler_panic_duel() {
+        let mut duel_builder = DuelBuilder::default();
+        duel_builder.set_script_handler(|_, name| panic!("no script for {name}"));
+        let duel = duel_builder.build();
+        let panic = catch_unwind(AssertUnwindSafe(|| {
+            duel.new_card(NewCardInfo {
+                code: 70095154,
+                ..Default::default()
+            })
+        }))
+        .unwrap_err();
+        assert_eq!(panic_message(&*panic), "no script for c70095154.lua");
+        // The duel is still usable after the panic has been resumed.
+        assert!(duel.load_script("Debug.ReloadFieldEnd()", " ").is_ok());
+    }
+    #[test]
     fn test_new_card_duel() {
         let mut duel_builder = DuelBuilder::default();
         let setcodes: HashSet<u16, RandomState> = HashSet::from_iter([1u16]);
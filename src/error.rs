@@ -6,4 +6,66 @@ pub enum DuelError {
     NulError(#[from] std::ffi::NulError),
     #[error("The Lua script `{0}` failed to load")]
     ScriptLoadFailure(String),
+    #[error("The Lua script `{0}` was not found")]
+    ScriptNotFound(String),
+    #[error("The Lua script name `{0}` is not a relative path inside the script directories")]
+    InvalidScriptName(String),
+    #[error("The Lua script `{name}` could not be read: {source}")]
+    ScriptRead {
+        name: String,
+        source: std::io::Error,
+    },
+    #[error("The duel could not be created: the engine reported no output")]
+    CreationNoOutput,
+    #[error("The duel could not be created")]
+    CreationNotCreated,
+    #[error("The duel could not be created: no card reader was provided")]
+    CreationNullDataReader,
+    #[error("The duel could not be created: no script reader was provided")]
+    CreationNullScriptReader,
+    #[error("The duel could not be created: unknown creation status {0}")]
+    CreationUnknown(i32),
+    #[error("The startup script `{name}` failed to load: {source}")]
+    StartupScriptFailure {
+        name: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("A handler panicked while the engine was calling it: {0}")]
+    HandlerPanic(String),
+    #[error("The duel is not waiting for a response")]
+    NotAwaitingResponse,
+    #[error("Player {expected} is expected to respond, not player {player}")]
+    UnexpectedResponder { expected: u8, player: u8 },
+    #[error("The duel worker stopped before answering")]
+    WorkerStopped,
+    #[error("The duel was still processing after {0} iterations")]
+    IterationBudgetExceeded(usize),
+    #[error(
+        "Card {code} was added to location {loc:#x}, but replays only store decks and extra decks"
+    )]
+    ReplayCardLocation { code: u32, loc: u32 },
+    #[error(
+        "Card {code} was added with a team, controller, sequence or position replays cannot store"
+    )]
+    ReplayCardPlacement { code: u32 },
+    #[error("Card {code} was added after a card of a later team, duelist or of an extra deck, but replays store cards grouped in that order")]
+    ReplayCardOrder { code: u32 },
+    #[error(
+        "Both teams must start with the same LP and draw counts, as replays only store them once"
+    )]
+    ReplayTeamMismatch,
+    #[error("The duel was not created through a forkable recorder")]
+    NotForkable,
+    #[error("The replay is invalid: {0}")]
+    InvalidReplay(String),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("The buffer ended unexpectedly at offset {offset}, {needed} more byte(s) were needed")]
+    BufferUnderflow { offset: usize, needed: usize },
+    #[cfg(feature = "cdb")]
+    #[error("{0}")]
+    Database(#[from] rusqlite::Error),
+    #[cfg(feature = "zip")]
+    #[error("{0}")]
+    Archive(#[from] zip::result::ZipError),
 }
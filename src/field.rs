@@ -0,0 +1,170 @@
+use crate::buffer::BufferReader;
+use crate::error::DuelError;
+use crate::message::LocInfo;
+
+/// A card occupying a monster or spell/trap zone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZoneCard {
+    pub position: u8,
+    /// The number of Xyz materials attached to the card.
+    pub overlay_count: u32,
+}
+
+impl ZoneCard {
+    fn read(reader: &mut BufferReader) -> Result<Option<ZoneCard>, DuelError> {
+        if !reader.read_bool()? {
+            return Ok(None);
+        }
+        Ok(Some(ZoneCard {
+            position: reader.read_u8()?,
+            overlay_count: reader.read_u32()?,
+        }))
+    }
+}
+
+/// One player's side of a [`FieldSnapshot`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlayerField {
+    pub lp: u32,
+    /// Main monster zones 0-4, followed by the two Extra Monster Zones.
+    pub monster_zones: [Option<ZoneCard>; 7],
+    /// Spell/trap zones 0-4, followed by the field zone and the two separate pendulum zones.
+    pub spell_zones: [Option<ZoneCard>; 8],
+    pub deck_count: u32,
+    pub hand_count: u32,
+    pub grave_count: u32,
+    pub removed_count: u32,
+    pub extra_count: u32,
+    /// The number of face-up pendulum monsters in the extra deck.
+    pub extra_faceup_count: u32,
+}
+
+impl PlayerField {
+    fn read(reader: &mut BufferReader) -> Result<PlayerField, DuelError> {
+        let mut field = PlayerField {
+            lp: reader.read_u32()?,
+            ..Default::default()
+        };
+        for zone in field.monster_zones.iter_mut() {
+            *zone = ZoneCard::read(reader)?;
+        }
+        for zone in field.spell_zones.iter_mut() {
+            *zone = ZoneCard::read(reader)?;
+        }
+        field.deck_count = reader.read_u32()?;
+        field.hand_count = reader.read_u32()?;
+        field.grave_count = reader.read_u32()?;
+        field.removed_count = reader.read_u32()?;
+        field.extra_count = reader.read_u32()?;
+        field.extra_faceup_count = reader.read_u32()?;
+        Ok(field)
+    }
+    pub fn field_zone(&self) -> Option<ZoneCard> {
+        self.spell_zones[5]
+    }
+    /// The zones only used by [`DUEL_SEPARATE_PZONE`](crate::common::DUEL_SEPARATE_PZONE).
+    /// Under later master rules pendulum scales occupy spell/trap zones 0 and 4 instead.
+    pub fn pendulum_zones(&self) -> [Option<ZoneCard>; 2] {
+        [self.spell_zones[6], self.spell_zones[7]]
+    }
+}
+
+/// A link of the chain that is currently being built or resolved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChainLink {
+    pub code: u32,
+    pub loc_info: LocInfo,
+    pub triggering_con: u8,
+    pub triggering_loc: u32,
+    pub triggering_seq: u32,
+    pub desc: u64,
+}
+
+impl ChainLink {
+    fn read(reader: &mut BufferReader) -> Result<ChainLink, DuelError> {
+        Ok(ChainLink {
+            code: reader.read_u32()?,
+            loc_info: LocInfo::read(reader)?,
+            triggering_con: reader.read_u8()?,
+            triggering_loc: reader.read_u8()? as u32,
+            triggering_seq: reader.read_u32()?,
+            desc: reader.read_u64()?,
+        })
+    }
+}
+
+/// The decoded buffer returned by [`Duel::query_field`](crate::Duel::query_field).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldSnapshot {
+    /// The duel flags the duel was created with, truncated to 32 bits.
+    pub options: u32,
+    pub players: [PlayerField; 2],
+    /// The current chain, from the first link to the last.
+    pub chain: Vec<ChainLink>,
+}
+
+impl FieldSnapshot {
+    pub fn parse(buffer: &[u8]) -> Result<FieldSnapshot, DuelError> {
+        let mut reader = BufferReader::new(buffer);
+        let options = reader.read_u32()?;
+        let players = [
+            PlayerField::read(&mut reader)?,
+            PlayerField::read(&mut reader)?,
+        ];
+        let count = reader.read_u32()? as usize;
+        let chain = reader.read_vec(count, ChainLink::read)?;
+        Ok(FieldSnapshot {
+            options,
+            players,
+            chain,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::*;
+
+    #[test]
+    fn test_parse_field_snapshot() {
+        let mut buffer = 0u32.to_le_bytes().to_vec();
+        for player in 0..2u32 {
+            buffer.extend_from_slice(&(8000 - player * 1000).to_le_bytes());
+            // A face-up attack monster with two materials in the first monster zone.
+            buffer.extend_from_slice(&[1, POS_FACEUP_ATTACK]);
+            buffer.extend_from_slice(&2u32.to_le_bytes());
+            buffer.extend_from_slice(&[0; 6 + 8]);
+            for count in [35, 5, 0, 0, 15, 0u32] {
+                buffer.extend_from_slice(&count.to_le_bytes());
+            }
+        }
+        buffer.extend_from_slice(&1u32.to_le_bytes());
+        buffer.extend_from_slice(&70095154u32.to_le_bytes());
+        buffer.extend_from_slice(&[0, LOCATION_MZONE as u8]);
+        buffer.extend_from_slice(&0u32.to_le_bytes());
+        buffer.extend_from_slice(&(POS_FACEUP_ATTACK as u32).to_le_bytes());
+        buffer.extend_from_slice(&[0, LOCATION_MZONE as u8]);
+        buffer.extend_from_slice(&0u32.to_le_bytes());
+        buffer.extend_from_slice(&(70095154u64 << 20).to_le_bytes());
+        let snapshot = FieldSnapshot::parse(&buffer).unwrap();
+        assert_eq!(snapshot.players[1].lp, 7000);
+        assert_eq!(
+            snapshot.players[0].monster_zones[0],
+            Some(ZoneCard {
+                position: POS_FACEUP_ATTACK,
+                overlay_count: 2,
+            })
+        );
+        assert!(snapshot.players[0].field_zone().is_none());
+        assert_eq!(snapshot.players[1].deck_count, 35);
+        assert_eq!(snapshot.players[1].extra_count, 15);
+        assert_eq!(snapshot.chain.len(), 1);
+        assert_eq!(snapshot.chain[0].desc, 70095154u64 << 20);
+        assert!(FieldSnapshot::parse(&buffer[..buffer.len() - 1]).is_err());
+    }
+}
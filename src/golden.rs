@@ -0,0 +1,281 @@
+//! Regression tests replaying recorded duels.
+//!
+//! A golden replay is a `.yrpX` file recorded with a [`ReplayRecorder`](crate::ReplayRecorder),
+//! whose messages are the expected outcome of the duel.
+//! Simulating it again after updating the engine or the scripts shows every duel that now plays differently.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::duel::DuelBuilder;
+use crate::error::DuelError;
+use crate::message::Message;
+use crate::replay::{Replay, ReplayPlayer};
+use crate::run::{decode_message, DuelResult};
+
+/// Why a golden replay failed.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum GoldenMismatch {
+    /// The simulation produced a different message than the one recorded.
+    Message {
+        index: usize,
+        /// The recorded message, or [`None`] if the simulation produced more messages.
+        expected: Option<Message>,
+        /// The simulated message, or [`None`] if the simulation produced fewer messages.
+        actual: Option<Message>,
+        expected_result: Option<DuelResult>,
+        actual_result: Option<DuelResult>,
+    },
+    /// The replay could not be read or simulated.
+    Error(DuelError),
+}
+
+/// The outcome of [`check_golden_replay`].
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum GoldenCheck {
+    /// The simulation produced every recorded message.
+    Passed,
+    /// The replay has no recorded messages, such as `.yrp` replays,
+    /// so it was only simulated without errors.
+    Unchecked,
+    Mismatch(GoldenMismatch),
+}
+
+#[derive(Debug)]
+pub struct GoldenFailure {
+    pub path: PathBuf,
+    pub mismatch: GoldenMismatch,
+}
+
+impl fmt::Display for GoldenFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.mismatch {
+            GoldenMismatch::Message {
+                index,
+                expected,
+                actual,
+                expected_result,
+                actual_result,
+            } => {
+                writeln!(f, "{}: message #{index} differs", self.path.display())?;
+                writeln!(f, "  expected: {expected:?}")?;
+                writeln!(f, "  actual:   {actual:?}")?;
+                write!(
+                    f,
+                    "  result: expected {expected_result:?}, actual {actual_result:?}"
+                )
+            }
+            GoldenMismatch::Error(err) => write!(f, "{}: {err}", self.path.display()),
+        }
+    }
+}
+
+/// The result of [`check_golden_replays`].
+#[derive(Debug, Default)]
+pub struct GoldenReport {
+    /// Every replay that was simulated, in order.
+    pub checked: Vec<PathBuf>,
+    /// The replays among `checked` without recorded messages to compare.
+    pub unchecked: Vec<PathBuf>,
+    pub failures: Vec<GoldenFailure>,
+}
+
+impl GoldenReport {
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl fmt::Display for GoldenReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_success() {
+            write!(f, "{} golden replay(s) passed", self.checked.len())?;
+        } else {
+            write!(
+                f,
+                "{} of {} golden replay(s) changed",
+                self.failures.len(),
+                self.checked.len()
+            )?;
+        }
+        if !self.unchecked.is_empty() {
+            write!(
+                f,
+                ", {} without recorded messages were only simulated",
+                self.unchecked.len()
+            )?;
+        }
+        for failure in &self.failures {
+            write!(f, "\n{failure}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Simulates every `.yrpX` and `.yrp` replay in the directory again, in file name order,
+/// and compares the decoded messages with the recorded ones.
+///
+/// `make_builder` is used as in [`ReplayPlayer::new`].
+/// Replays without recorded messages, such as `.yrp` replays, only have to simulate without errors,
+/// and are listed in [`GoldenReport::unchecked`].
+/// Returns an error only if the directory cannot be read.
+pub fn check_golden_replays<P, F>(directory: P, make_builder: F) -> Result<GoldenReport, DuelError>
+where
+    P: AsRef<Path>,
+    F: Fn() -> DuelBuilder + Clone + 'static,
+{
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let is_replay = path.extension().is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("yrpx") || extension.eq_ignore_ascii_case("yrp")
+        });
+        if is_replay && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    let mut report = GoldenReport::default();
+    for path in paths {
+        let mismatch = match check_golden_replay(&path, make_builder.clone()) {
+            Ok(GoldenCheck::Passed) => None,
+            Ok(GoldenCheck::Unchecked) => {
+                report.unchecked.push(path.clone());
+                None
+            }
+            Ok(GoldenCheck::Mismatch(mismatch)) => Some(mismatch),
+            Err(err) => Some(GoldenMismatch::Error(err)),
+        };
+        if let Some(mismatch) = mismatch {
+            report.failures.push(GoldenFailure {
+                path: path.clone(),
+                mismatch,
+            });
+        }
+        report.checked.push(path);
+    }
+    Ok(report)
+}
+
+/// Simulates one golden replay again, returning the first message that differs.
+pub fn check_golden_replay<P, F>(path: P, make_builder: F) -> Result<GoldenCheck, DuelError>
+where
+    P: AsRef<Path>,
+    F: FnMut() -> DuelBuilder + 'static,
+{
+    let replay = Replay::open(path)?;
+    // Recorded messages are decoded like simulated ones, so a malformed record shows up in the diff.
+    let expected: Vec<Message> = replay
+        .messages
+        .iter()
+        .filter_map(|message| decode_message(message))
+        .collect();
+    let mut actual = Vec::new();
+    for run in ReplayPlayer::new(replay, make_builder)? {
+        actual.extend(run?.messages);
+    }
+    if expected.is_empty() {
+        return Ok(GoldenCheck::Unchecked);
+    }
+    let Some(index) = (0..expected.len().max(actual.len()))
+        .find(|&index| expected.get(index) != actual.get(index))
+    else {
+        return Ok(GoldenCheck::Passed);
+    };
+    Ok(GoldenCheck::Mismatch(GoldenMismatch::Message {
+        index,
+        expected: expected.get(index).cloned(),
+        actual: actual.get(index).cloned(),
+        expected_result: DuelResult::find(&expected),
+        actual_result: DuelResult::find(&actual),
+    }))
+}
+
+/// Defines a test failing with a report of every golden replay in a directory that changed,
+/// see [`check_golden_replays`].
+///
+/// ```no_run
+/// use ygopro_core_rs::{golden_replay_test, DuelBuilder};
+///
+/// fn make_builder() -> DuelBuilder {
+///     let mut builder = DuelBuilder::new();
+///     // Set the card and script handlers...
+///     builder
+/// }
+///
+/// golden_replay_test!(golden_replays, "tests/replays", make_builder);
+/// ```
+#[macro_export]
+macro_rules! golden_replay_test {
+    ($name:ident, $directory:expr, $make_builder:expr $(,)?) => {
+        #[test]
+        fn $name() {
+            let report = $crate::check_golden_replays($directory, $make_builder)
+                .unwrap_or_else(|err| panic!("failed to read the golden replays: {err}"));
+            assert!(report.is_success(), "{report}");
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::MSG_WIN;
+    use crate::replay::record_duel_without_cards;
+    use crate::response::{IdleCmdAction, Response};
+
+    #[test]
+    fn test_check_golden_replays() {
+        let directory = std::env::temp_dir().join(format!("ygopro-golden-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let mut recorder = record_duel_without_cards();
+        recorder.respond(&Response::SelectIdleCmd(IdleCmdAction::EndPhase));
+        recorder.process_until_input();
+        let mut replay = recorder.into_replay();
+        replay.save(directory.join("a.yrpX")).unwrap();
+        let index = replay.messages.len() - 1;
+        let recorded = Message::parse(&replay.messages[index]).unwrap();
+        // A truncated record still shows up in the diff.
+        replay.messages[index] = vec![MSG_WIN, 2];
+        replay.save(directory.join("b.yrpX")).unwrap();
+        fs::write(directory.join("c.yrp"), replay.to_yrp_bytes().unwrap()).unwrap();
+        fs::write(directory.join("d.yrpX"), b"yrpX").unwrap();
+        fs::write(directory.join("notes.txt"), "").unwrap();
+
+        let report = check_golden_replays(&directory, DuelBuilder::new).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(report.checked.len(), 4);
+        assert_eq!(report.unchecked, [directory.join("c.yrp")]);
+        assert_eq!(report.failures.len(), 2, "{report}");
+        assert_eq!(report.failures[0].path, directory.join("b.yrpX"));
+        let GoldenMismatch::Message {
+            index: mismatch_index,
+            expected,
+            actual,
+            expected_result,
+            actual_result,
+        } = &report.failures[0].mismatch
+        else {
+            panic!("{report}");
+        };
+        assert_eq!(*mismatch_index, index);
+        assert_eq!(
+            expected,
+            &Some(Message::Malformed {
+                id: MSG_WIN,
+                bytes: vec![2]
+            })
+        );
+        assert_eq!(actual, &Some(recorded));
+        assert_eq!(*expected_result, None);
+        assert!(actual_result.is_some());
+        assert!(matches!(
+            report.failures[1].mismatch,
+            GoldenMismatch::Error(DuelError::InvalidReplay(_))
+        ));
+        assert!(report.to_string().starts_with("2 of 4"));
+    }
+}
@@ -0,0 +1,574 @@
+use crate::common::*;
+use crate::message::*;
+use crate::response::{BattleCmdAction, IdleCmdAction, Place, Response};
+
+/// Enumerates every [`Response`] the engine would accept for a select prompt.
+///
+/// Bots, UIs and fuzzers should all use this as the single source of truth for legal moves.
+/// Prompts that pick subsets or orderings of cards grow combinatorially,
+/// so large selections can produce very large lists.
+pub trait LegalResponses {
+    fn legal_responses(&self) -> Vec<Response>;
+}
+
+/// Every `k` sized combination of `0..n`, in lexicographic order.
+fn combinations(n: usize, k: usize) -> Vec<Vec<u32>> {
+    let mut result = Vec::new();
+    if k > n {
+        return result;
+    }
+    let mut indices: Vec<usize> = (0..k).collect();
+    loop {
+        result.push(indices.iter().map(|&i| i as u32).collect());
+        let mut i = k;
+        while i > 0 && indices[i - 1] == n - k + i - 1 {
+            i -= 1;
+        }
+        if i == 0 {
+            return result;
+        }
+        indices[i - 1] += 1;
+        for j in i..k {
+            indices[j] = indices[j - 1] + 1;
+        }
+    }
+}
+
+/// Every combination of `0..n` with a size in `min..=max`.
+fn subsets(n: usize, min: usize, max: usize) -> Vec<Vec<u32>> {
+    (min..=max.min(n))
+        .flat_map(|k| combinations(n, k))
+        .collect()
+}
+
+fn permutations(n: usize) -> Vec<Vec<u8>> {
+    fn permute(prefix: &mut Vec<u8>, remaining: &mut Vec<u8>, result: &mut Vec<Vec<u8>>) {
+        if remaining.is_empty() {
+            result.push(prefix.clone());
+            return;
+        }
+        for i in 0..remaining.len() {
+            let next = remaining.remove(i);
+            prefix.push(next);
+            permute(prefix, remaining, result);
+            prefix.pop();
+            remaining.insert(i, next);
+        }
+    }
+    let mut result = Vec::new();
+    permute(&mut Vec::new(), &mut (0..n as u8).collect(), &mut result);
+    result
+}
+
+/// Every value made of exactly `count` of the bits set in `mask`.
+fn bit_combinations(mask: u64, count: usize) -> Vec<u64> {
+    let bits: Vec<u64> = (0..64)
+        .map(|i| 1u64 << i)
+        .filter(|bit| mask & bit != 0)
+        .collect();
+    combinations(bits.len(), count)
+        .into_iter()
+        .map(|combination| combination.iter().map(|&i| bits[i as usize]).sum())
+        .collect()
+}
+
+impl LegalResponses for SelectIdleCmd {
+    fn legal_responses(&self) -> Vec<Response> {
+        let indices = |len: usize, action: fn(u32) -> IdleCmdAction| (0..len as u32).map(action);
+        let mut actions: Vec<IdleCmdAction> = indices(self.summonable.len(), IdleCmdAction::Summon)
+            .chain(indices(
+                self.special_summonable.len(),
+                IdleCmdAction::SpecialSummon,
+            ))
+            .chain(indices(
+                self.repositionable.len(),
+                IdleCmdAction::Reposition,
+            ))
+            .chain(indices(
+                self.monster_setable.len(),
+                IdleCmdAction::MonsterSet,
+            ))
+            .chain(indices(self.spell_setable.len(), IdleCmdAction::SpellSet))
+            .chain(indices(self.activatable.len(), IdleCmdAction::Activate))
+            .collect();
+        if self.can_battle_phase {
+            actions.push(IdleCmdAction::BattlePhase);
+        }
+        if self.can_end_phase {
+            actions.push(IdleCmdAction::EndPhase);
+        }
+        if self.can_shuffle {
+            actions.push(IdleCmdAction::Shuffle);
+        }
+        actions.into_iter().map(Response::SelectIdleCmd).collect()
+    }
+}
+
+impl LegalResponses for SelectBattleCmd {
+    fn legal_responses(&self) -> Vec<Response> {
+        let mut actions: Vec<BattleCmdAction> = (0..self.activatable.len() as u32)
+            .map(BattleCmdAction::Activate)
+            .chain((0..self.attackable.len() as u32).map(BattleCmdAction::Attack))
+            .collect();
+        if self.can_main2 {
+            actions.push(BattleCmdAction::Main2);
+        }
+        if self.can_end_phase {
+            actions.push(BattleCmdAction::EndPhase);
+        }
+        actions.into_iter().map(Response::SelectBattleCmd).collect()
+    }
+}
+
+impl LegalResponses for SelectEffectYn {
+    fn legal_responses(&self) -> Vec<Response> {
+        vec![
+            Response::SelectEffectYn(true),
+            Response::SelectEffectYn(false),
+        ]
+    }
+}
+
+impl LegalResponses for SelectYesNo {
+    fn legal_responses(&self) -> Vec<Response> {
+        vec![Response::SelectYesNo(true), Response::SelectYesNo(false)]
+    }
+}
+
+impl LegalResponses for SelectOption {
+    fn legal_responses(&self) -> Vec<Response> {
+        (0..self.options.len() as u32)
+            .map(Response::SelectOption)
+            .collect()
+    }
+}
+
+impl LegalResponses for SelectCard {
+    fn legal_responses(&self) -> Vec<Response> {
+        let mut responses: Vec<Response> =
+            subsets(self.cards.len(), self.min as usize, self.max as usize)
+                .into_iter()
+                .map(|indices| Response::SelectCard(Some(indices)))
+                .collect();
+        if self.cancelable {
+            responses.push(Response::SelectCard(None));
+        }
+        responses
+    }
+}
+
+impl LegalResponses for SelectTribute {
+    /// A selection is legal when its total `release_param` reaches `min`,
+    /// and it either stays within `max` or every card in it is needed to reach `min`.
+    fn legal_responses(&self) -> Vec<Response> {
+        let value = |index: u32| self.cards[index as usize].release_param.max(1) as u32;
+        let mut responses: Vec<Response> = subsets(self.cards.len(), 1, self.max as usize)
+            .into_iter()
+            .filter(|indices| {
+                let total: u32 = indices.iter().map(|&i| value(i)).sum();
+                total >= self.min
+                    && (total <= self.max || indices.iter().all(|&i| total - value(i) < self.min))
+            })
+            .map(|indices| Response::SelectTribute(Some(indices)))
+            .collect();
+        if self.cancelable {
+            responses.push(Response::SelectTribute(None));
+        }
+        responses
+    }
+}
+
+/// The one or two values a card can contribute to a [`SelectSum`].
+fn sum_values(card: &SumCard) -> Vec<u32> {
+    let low = card.param & 0xffff;
+    let high = card.param >> 16;
+    if high == 0 || high == low {
+        vec![low]
+    } else {
+        vec![low, high]
+    }
+}
+
+impl SelectSum {
+    fn is_legal(&self, indices: &[u32]) -> bool {
+        let cards: Vec<Vec<u32>> = self
+            .must_select
+            .iter()
+            .map(sum_values)
+            .chain(
+                indices
+                    .iter()
+                    .map(|&i| sum_values(&self.selectable[i as usize])),
+            )
+            .collect();
+        let must_count = self.must_select.len();
+        // Try every combination of alternative values.
+        let mut choice = vec![0usize; cards.len()];
+        loop {
+            let values: Vec<u32> = cards.iter().zip(&choice).map(|(v, &c)| v[c]).collect();
+            let total: u32 = values.iter().sum();
+            let legal = if self.select_mode == 0 {
+                total == self.sum
+            } else {
+                total >= self.sum && values[must_count..].iter().all(|v| total - v < self.sum)
+            };
+            if legal {
+                return true;
+            }
+            let mut i = 0;
+            while i < cards.len() && choice[i] + 1 == cards[i].len() {
+                choice[i] = 0;
+                i += 1;
+            }
+            if i == cards.len() {
+                return false;
+            }
+            choice[i] += 1;
+        }
+    }
+}
+
+impl LegalResponses for SelectSum {
+    fn legal_responses(&self) -> Vec<Response> {
+        let must_count = self.must_select.len();
+        let min = (self.min as usize).saturating_sub(must_count);
+        let max = (self.max as usize).saturating_sub(must_count);
+        subsets(self.selectable.len(), min, max)
+            .into_iter()
+            .filter(|indices| self.is_legal(indices))
+            .map(Response::SelectSum)
+            .collect()
+    }
+}
+
+impl LegalResponses for SelectUnselectCard {
+    fn legal_responses(&self) -> Vec<Response> {
+        let count = self.selectable.len() + self.unselectable.len();
+        let mut responses: Vec<Response> = (0..count as u32)
+            .map(|index| Response::SelectUnselectCard(Some(index)))
+            .collect();
+        if self.finishable || self.cancelable {
+            responses.push(Response::SelectUnselectCard(None));
+        }
+        responses
+    }
+}
+
+impl LegalResponses for SelectChain {
+    fn legal_responses(&self) -> Vec<Response> {
+        let mut responses: Vec<Response> = (0..self.chains.len() as u32)
+            .map(|index| Response::SelectChain(Some(index)))
+            .collect();
+        if !self.forced {
+            responses.push(Response::SelectChain(None));
+        }
+        responses
+    }
+}
+
+impl SelectPlace {
+    /// Every zone that is not masked out by [`flag`](#structfield.flag).
+    pub fn available_places(&self) -> Vec<Place> {
+        (0..32u32)
+            .filter(|bit| self.flag & (1 << bit) == 0)
+            // There is no eighth monster zone.
+            .filter(|bit| bit % 16 != 7)
+            .map(|bit| Place {
+                con: if bit < 16 {
+                    self.player
+                } else {
+                    1 - self.player
+                },
+                loc: if bit % 16 < 8 {
+                    LOCATION_MZONE
+                } else {
+                    LOCATION_SZONE
+                },
+                seq: (bit % 8) as u8,
+            })
+            .collect()
+    }
+    fn place_combinations(&self) -> Vec<Vec<Place>> {
+        let places = self.available_places();
+        combinations(places.len(), self.count.max(1) as usize)
+            .into_iter()
+            .map(|indices| indices.iter().map(|&i| places[i as usize]).collect())
+            .collect()
+    }
+}
+
+impl LegalResponses for SelectPlace {
+    fn legal_responses(&self) -> Vec<Response> {
+        self.place_combinations()
+            .into_iter()
+            .map(Response::SelectPlace)
+            .collect()
+    }
+}
+
+impl LegalResponses for SelectPosition {
+    fn legal_responses(&self) -> Vec<Response> {
+        [
+            POS_FACEUP_ATTACK,
+            POS_FACEDOWN_ATTACK,
+            POS_FACEUP_DEFENSE,
+            POS_FACEDOWN_DEFENSE,
+        ]
+        .into_iter()
+        .filter(|pos| self.positions & pos != 0)
+        .map(Response::SelectPosition)
+        .collect()
+    }
+}
+
+impl LegalResponses for SelectCounter {
+    fn legal_responses(&self) -> Vec<Response> {
+        fn distribute(
+            cards: &[CounterCard],
+            remaining: u16,
+            current: &mut Vec<u16>,
+            result: &mut Vec<Response>,
+        ) {
+            let Some((card, rest)) = cards.split_first() else {
+                if remaining == 0 {
+                    result.push(Response::SelectCounter(current.clone()));
+                }
+                return;
+            };
+            for count in 0..=card.counter_count.min(remaining) {
+                current.push(count);
+                distribute(rest, remaining - count, current, result);
+                current.pop();
+            }
+        }
+        let mut result = Vec::new();
+        distribute(&self.cards, self.count, &mut Vec::new(), &mut result);
+        result
+    }
+}
+
+impl SortCard {
+    fn orders(&self) -> Vec<Option<Vec<u8>>> {
+        std::iter::once(None)
+            .chain(permutations(self.cards.len()).into_iter().map(Some))
+            .collect()
+    }
+}
+
+impl LegalResponses for AnnounceRace {
+    fn legal_responses(&self) -> Vec<Response> {
+        bit_combinations(self.available, self.count as usize)
+            .into_iter()
+            .map(Response::AnnounceRace)
+            .collect()
+    }
+}
+
+impl LegalResponses for AnnounceAttrib {
+    fn legal_responses(&self) -> Vec<Response> {
+        bit_combinations(self.available as u64, self.count as usize)
+            .into_iter()
+            .map(|attribute| Response::AnnounceAttrib(attribute as u32))
+            .collect()
+    }
+}
+
+impl LegalResponses for AnnounceCard {
+    /// Always empty: which codes satisfy the opcodes depends on the card database,
+    /// so the legal codes have to be filtered by the caller.
+    fn legal_responses(&self) -> Vec<Response> {
+        Vec::new()
+    }
+}
+
+impl LegalResponses for AnnounceNumber {
+    fn legal_responses(&self) -> Vec<Response> {
+        (0..self.numbers.len() as u32)
+            .map(Response::AnnounceNumber)
+            .collect()
+    }
+}
+
+impl LegalResponses for RockPaperScissors {
+    fn legal_responses(&self) -> Vec<Response> {
+        (1..=3).map(Response::RockPaperScissors).collect()
+    }
+}
+
+impl Message {
+    /// Returns the player expected to respond to this message,
+    /// or [`None`] if the message is not a prompt that expects a response.
+    pub fn prompt_player(&self) -> Option<u8> {
+        let player = match self {
+            Message::SelectIdleCmd(prompt) => prompt.player,
+            Message::SelectBattleCmd(prompt) => prompt.player,
+            Message::SelectEffectYn(prompt) => prompt.player,
+            Message::SelectYesNo(prompt) => prompt.player,
+            Message::SelectOption(prompt) => prompt.player,
+            Message::SelectCard(prompt) => prompt.player,
+            Message::SelectTribute(prompt) => prompt.player,
+            Message::SelectSum(prompt) => prompt.player,
+            Message::SelectUnselectCard(prompt) => prompt.player,
+            Message::SelectChain(prompt) => prompt.player,
+            Message::SelectPlace(prompt) => prompt.player,
+            Message::SelectDisfield(prompt) => prompt.player,
+            Message::SelectPosition(prompt) => prompt.player,
+            Message::SelectCounter(prompt) => prompt.player,
+            Message::SortCard(prompt) => prompt.player,
+            Message::SortChain(prompt) => prompt.player,
+            Message::AnnounceRace(prompt) => prompt.player,
+            Message::AnnounceAttrib(prompt) => prompt.player,
+            Message::AnnounceCard(prompt) => prompt.player,
+            Message::AnnounceNumber(prompt) => prompt.player,
+            Message::RockPaperScissors(prompt) => prompt.player,
+            _ => return None,
+        };
+        Some(player)
+    }
+    /// Returns every legal response to this message,
+    /// or [`None`] if the message is not a prompt that expects a response.
+    pub fn legal_responses(&self) -> Option<Vec<Response>> {
+        let responses = match self {
+            Message::SelectIdleCmd(prompt) => prompt.legal_responses(),
+            Message::SelectBattleCmd(prompt) => prompt.legal_responses(),
+            Message::SelectEffectYn(prompt) => prompt.legal_responses(),
+            Message::SelectYesNo(prompt) => prompt.legal_responses(),
+            Message::SelectOption(prompt) => prompt.legal_responses(),
+            Message::SelectCard(prompt) => prompt.legal_responses(),
+            Message::SelectTribute(prompt) => prompt.legal_responses(),
+            Message::SelectSum(prompt) => prompt.legal_responses(),
+            Message::SelectUnselectCard(prompt) => prompt.legal_responses(),
+            Message::SelectChain(prompt) => prompt.legal_responses(),
+            Message::SelectPlace(prompt) => prompt.legal_responses(),
+            Message::SelectDisfield(prompt) => prompt
+                .place_combinations()
+                .into_iter()
+                .map(Response::SelectDisfield)
+                .collect(),
+            Message::SelectPosition(prompt) => prompt.legal_responses(),
+            Message::SelectCounter(prompt) => prompt.legal_responses(),
+            Message::SortCard(prompt) => prompt
+                .orders()
+                .into_iter()
+                .map(Response::SortCard)
+                .collect(),
+            Message::SortChain(prompt) => prompt
+                .orders()
+                .into_iter()
+                .map(Response::SortChain)
+                .collect(),
+            Message::AnnounceRace(prompt) => prompt.legal_responses(),
+            Message::AnnounceAttrib(prompt) => prompt.legal_responses(),
+            Message::AnnounceCard(prompt) => prompt.legal_responses(),
+            Message::AnnounceNumber(prompt) => prompt.legal_responses(),
+            Message::RockPaperScissors(prompt) => prompt.legal_responses(),
+            _ => return None,
+        };
+        Some(responses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_cmd_responses() {
+        let prompt = SelectIdleCmd {
+            summonable: vec![CardLocation::default(); 2],
+            activatable: vec![ActivatableEffect::default()],
+            can_end_phase: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            prompt.legal_responses(),
+            vec![
+                Response::SelectIdleCmd(IdleCmdAction::Summon(0)),
+                Response::SelectIdleCmd(IdleCmdAction::Summon(1)),
+                Response::SelectIdleCmd(IdleCmdAction::Activate(0)),
+                Response::SelectIdleCmd(IdleCmdAction::EndPhase),
+            ]
+        );
+    }
+    #[test]
+    fn test_select_card_responses() {
+        let prompt = SelectCard {
+            cancelable: true,
+            min: 1,
+            max: 2,
+            cards: vec![CardInfo::default(); 3],
+            ..Default::default()
+        };
+        let responses = prompt.legal_responses();
+        // 3 singles, 3 pairs and the cancel.
+        assert_eq!(responses.len(), 7);
+        assert!(responses.contains(&Response::SelectCard(Some(vec![0, 2]))));
+        assert_eq!(responses.last(), Some(&Response::SelectCard(None)));
+    }
+    #[test]
+    fn test_select_sum_responses() {
+        let card = |param| SumCard {
+            param,
+            ..Default::default()
+        };
+        let prompt = SelectSum {
+            select_mode: 0,
+            sum: 6,
+            min: 1,
+            max: 3,
+            selectable: vec![card(2), card(4), card(6), card(1 | (4 << 16))],
+            ..Default::default()
+        };
+        assert_eq!(
+            prompt.legal_responses(),
+            vec![
+                Response::SelectSum(vec![2]),
+                Response::SelectSum(vec![0, 1]),
+                Response::SelectSum(vec![0, 3]),
+            ]
+        );
+    }
+    #[test]
+    fn test_select_place_responses() {
+        let prompt = SelectPlace {
+            player: 0,
+            count: 1,
+            // Only the player's first and third monster zones are free.
+            flag: !0b101,
+        };
+        assert_eq!(
+            prompt.legal_responses(),
+            vec![
+                Response::SelectPlace(vec![Place {
+                    con: 0,
+                    loc: LOCATION_MZONE,
+                    seq: 0,
+                }]),
+                Response::SelectPlace(vec![Place {
+                    con: 0,
+                    loc: LOCATION_MZONE,
+                    seq: 2,
+                }]),
+            ]
+        );
+    }
+    #[test]
+    fn test_select_counter_responses() {
+        let card = |counter_count| CounterCard {
+            counter_count,
+            ..Default::default()
+        };
+        let prompt = SelectCounter {
+            count: 2,
+            cards: vec![card(1), card(2)],
+            ..Default::default()
+        };
+        assert_eq!(
+            prompt.legal_responses(),
+            vec![
+                Response::SelectCounter(vec![0, 2]),
+                Response::SelectCounter(vec![1, 1]),
+            ]
+        );
+    }
+}
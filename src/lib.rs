@@ -1,14 +1,51 @@
 extern crate ygopro_core_rs_sys as ffi;
 
+mod buffer;
 pub mod card;
+#[cfg(feature = "cdb")]
+pub mod cdb;
 pub mod common;
+#[cfg(feature = "async")]
+pub mod driver;
 pub mod duel;
 pub mod error;
+pub mod field;
+#[cfg(feature = "replay")]
+pub mod golden;
+pub mod legal;
+pub mod logging;
+pub mod message;
 pub mod player;
 pub mod query;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod repository;
+pub mod response;
+pub mod run;
+pub mod script;
+pub mod snapshot;
+pub mod worker;
 
 pub use crate::card::*;
+#[cfg(feature = "cdb")]
+pub use crate::cdb::*;
+#[cfg(feature = "async")]
+pub use crate::driver::*;
 pub use crate::duel::*;
 pub use crate::error::*;
+pub use crate::field::*;
+#[cfg(feature = "replay")]
+pub use crate::golden::*;
+pub use crate::legal::*;
+pub use crate::logging::*;
+pub use crate::message::*;
 pub use crate::player::*;
 pub use crate::query::*;
+#[cfg(feature = "replay")]
+pub use crate::replay::*;
+pub use crate::repository::*;
+pub use crate::response::*;
+pub use crate::run::*;
+pub use crate::script::*;
+pub use crate::snapshot::*;
+pub use crate::worker::*;
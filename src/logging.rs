@@ -0,0 +1,140 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::duel::LogHandler;
+use crate::ffi::{
+    OCG_LogTypes_OCG_LOG_TYPE_ERROR, OCG_LogTypes_OCG_LOG_TYPE_FOR_DEBUG,
+    OCG_LogTypes_OCG_LOG_TYPE_FROM_SCRIPT, OCG_LogTypes_OCG_LOG_TYPE_UNDEFINED,
+};
+
+/// The kind of a message passed to the [`LogHandler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogType {
+    /// A Lua error, such as a card script failing to compile or run.
+    Error = OCG_LogTypes_OCG_LOG_TYPE_ERROR as isize,
+    /// A message printed by a script with `Debug.Message`.
+    FromScript = OCG_LogTypes_OCG_LOG_TYPE_FROM_SCRIPT as isize,
+    ForDebug = OCG_LogTypes_OCG_LOG_TYPE_FOR_DEBUG as isize,
+    Undefined = OCG_LogTypes_OCG_LOG_TYPE_UNDEFINED as isize,
+}
+
+impl From<i32> for LogType {
+    fn from(log_type: i32) -> Self {
+        #![allow(non_upper_case_globals)]
+        match log_type as u32 {
+            OCG_LogTypes_OCG_LOG_TYPE_ERROR => LogType::Error,
+            OCG_LogTypes_OCG_LOG_TYPE_FROM_SCRIPT => LogType::FromScript,
+            OCG_LogTypes_OCG_LOG_TYPE_FOR_DEBUG => LogType::ForDebug,
+            _ => LogType::Undefined,
+        }
+    }
+}
+
+/// Returns a log handler forwarding to the [`log`](https://docs.rs/log) facade,
+/// with the provided duel id as the `duel_id` key-value.
+///
+/// Errors are logged at the error level, script messages at the info level,
+/// debug messages at the debug level and anything else at the warn level.
+#[cfg(feature = "log")]
+pub fn log_handler<D: std::fmt::Display>(duel_id: D) -> impl LogHandler {
+    let duel_id = duel_id.to_string();
+    move |msg: &str, log_type: LogType| {
+        let level = match log_type {
+            LogType::Error => ::log::Level::Error,
+            LogType::FromScript => ::log::Level::Info,
+            LogType::ForDebug => ::log::Level::Debug,
+            LogType::Undefined => ::log::Level::Warn,
+        };
+        ::log::log!(target: "ygopro_core", level, duel_id = duel_id.as_str(); "{msg}");
+    }
+}
+
+/// Returns a log handler emitting [`tracing`](https://docs.rs/tracing) events,
+/// with the provided duel id as the `duel_id` field.
+///
+/// Uses the same levels as [`log_handler`](fn.log_handler.html).
+#[cfg(feature = "tracing")]
+pub fn tracing_handler<D: std::fmt::Display>(duel_id: D) -> impl LogHandler {
+    use ::tracing::{event, Level};
+
+    let duel_id = duel_id.to_string();
+    move |msg: &str, log_type: LogType| match log_type {
+        LogType::Error => event!(target: "ygopro_core", Level::ERROR, duel_id, "{msg}"),
+        LogType::FromScript => event!(target: "ygopro_core", Level::INFO, duel_id, "{msg}"),
+        LogType::ForDebug => event!(target: "ygopro_core", Level::DEBUG, duel_id, "{msg}"),
+        LogType::Undefined => event!(target: "ygopro_core", Level::WARN, duel_id, "{msg}"),
+    }
+}
+
+/// Collects the [`LogType::Error`] messages of a duel, so tests can fail when any card script errors.
+///
+/// Cloning returns a handle to the same list.
+///
+/// ```
+/// use ygopro_core_rs::{DuelBuilder, ScriptErrors};
+///
+/// let errors = ScriptErrors::new();
+/// let mut duel_builder = DuelBuilder::new();
+/// duel_builder.set_log_handler(errors.wrap(|msg, _| println!("{msg}")));
+/// let duel = duel_builder.build();
+/// // Load scripts and run the duel...
+/// assert!(errors.is_empty(), "{:?}", errors.errors());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ScriptErrors {
+    errors: Arc<Mutex<Vec<String>>>,
+}
+
+impl ScriptErrors {
+    pub fn new() -> ScriptErrors {
+        ScriptErrors::default()
+    }
+    fn lock(errors: &Mutex<Vec<String>>) -> MutexGuard<'_, Vec<String>> {
+        errors.lock().unwrap_or_else(|err| err.into_inner())
+    }
+    /// Returns a log handler that only collects errors.
+    pub fn handler(&self) -> impl LogHandler {
+        self.wrap(|_: &str, _| ())
+    }
+    /// Returns a log handler that collects errors and passes every message on to `handler`.
+    pub fn wrap<F: LogHandler>(&self, mut handler: F) -> impl LogHandler {
+        let errors = self.errors.clone();
+        move |msg: &str, log_type: LogType| {
+            if log_type == LogType::Error {
+                Self::lock(&errors).push(msg.to_owned());
+            }
+            handler(msg, log_type)
+        }
+    }
+    /// Returns the errors collected so far.
+    pub fn errors(&self) -> Vec<String> {
+        Self::lock(&self.errors).clone()
+    }
+    /// Returns and clears the errors collected so far.
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *Self::lock(&self.errors))
+    }
+    pub fn len(&self) -> usize {
+        Self::lock(&self.errors).len()
+    }
+    pub fn is_empty(&self) -> bool {
+        Self::lock(&self.errors).is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_errors() {
+        assert_eq!(LogType::from(1), LogType::FromScript);
+        assert_eq!(LogType::from(42), LogType::Undefined);
+        let errors = ScriptErrors::new();
+        let mut handler = errors.handler();
+        handler("CARD_CYBER_DRAGON", LogType::FromScript);
+        handler("c1.lua:1: unexpected symbol", LogType::Error);
+        assert_eq!(errors.errors(), ["c1.lua:1: unexpected symbol"]);
+        assert_eq!(errors.take().len(), 1);
+        assert!(errors.is_empty());
+    }
+}
@@ -0,0 +1,1455 @@
+use crate::buffer::BufferReader;
+use crate::common::*;
+use crate::error::DuelError;
+
+/// The location of a card as written by ocgcore's `loc_info`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LocInfo {
+    pub con: u8,
+    pub loc: u32,
+    pub seq: u32,
+    pub pos: u32,
+}
+
+impl LocInfo {
+    pub(crate) fn read(reader: &mut BufferReader) -> Result<LocInfo, DuelError> {
+        Ok(LocInfo {
+            con: reader.read_u8()?,
+            loc: reader.read_u8()? as u32,
+            seq: reader.read_u32()?,
+            pos: reader.read_u32()?,
+        })
+    }
+}
+
+/// A card code paired with where it currently is, without its position.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CardLocation {
+    pub code: u32,
+    pub con: u8,
+    pub loc: u32,
+    pub seq: u32,
+}
+
+impl CardLocation {
+    fn read(reader: &mut BufferReader) -> Result<CardLocation, DuelError> {
+        Ok(CardLocation {
+            code: reader.read_u32()?,
+            con: reader.read_u8()?,
+            loc: reader.read_u8()? as u32,
+            seq: reader.read_u32()?,
+        })
+    }
+    /// Some messages still write the sequence as a single byte.
+    fn read_short(reader: &mut BufferReader) -> Result<CardLocation, DuelError> {
+        Ok(CardLocation {
+            code: reader.read_u32()?,
+            con: reader.read_u8()?,
+            loc: reader.read_u8()? as u32,
+            seq: reader.read_u8()? as u32,
+        })
+    }
+}
+
+/// A card code paired with its full [`LocInfo`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CardInfo {
+    pub code: u32,
+    pub loc_info: LocInfo,
+}
+
+impl CardInfo {
+    fn read(reader: &mut BufferReader) -> Result<CardInfo, DuelError> {
+        Ok(CardInfo {
+            code: reader.read_u32()?,
+            loc_info: LocInfo::read(reader)?,
+        })
+    }
+}
+
+/// A card code paired with its position, as written for draws and hand/extra reveals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CardCodePosition {
+    pub code: u32,
+    pub pos: u32,
+}
+
+impl CardCodePosition {
+    fn read(reader: &mut BufferReader) -> Result<CardCodePosition, DuelError> {
+        Ok(CardCodePosition {
+            code: reader.read_u32()?,
+            pos: reader.read_u32()?,
+        })
+    }
+}
+
+/// An effect that can be activated in response to an idle, battle or chain prompt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ActivatableEffect {
+    pub code: u32,
+    pub loc_info: LocInfo,
+    pub desc: u64,
+    /// One of the `EFFECT_CLIENT_MODE_*` constants.
+    pub client_mode: u8,
+}
+
+impl ActivatableEffect {
+    fn read(reader: &mut BufferReader) -> Result<ActivatableEffect, DuelError> {
+        Ok(ActivatableEffect {
+            code: reader.read_u32()?,
+            loc_info: LocInfo::read(reader)?,
+            desc: reader.read_u64()?,
+            client_mode: reader.read_u8()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AttackableCard {
+    pub card: CardLocation,
+    pub direct_attackable: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TributeCard {
+    pub card: CardLocation,
+    pub release_param: u8,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CounterCard {
+    pub card: CardLocation,
+    pub counter_count: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SumCard {
+    pub card: CardLocation,
+    /// The value(s) this card contributes to the sum.
+    /// The low and high 16 bits hold two alternative values when both are set.
+    pub param: u32,
+}
+
+impl SumCard {
+    fn read(reader: &mut BufferReader) -> Result<SumCard, DuelError> {
+        Ok(SumCard {
+            card: CardLocation::read(reader)?,
+            param: reader.read_u32()?,
+        })
+    }
+}
+
+/// Prompt sent with [`MSG_SELECT_BATTLECMD`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SelectBattleCmd {
+    pub player: u8,
+    pub activatable: Vec<ActivatableEffect>,
+    pub attackable: Vec<AttackableCard>,
+    pub can_main2: bool,
+    pub can_end_phase: bool,
+}
+
+/// Prompt sent with [`MSG_SELECT_IDLECMD`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SelectIdleCmd {
+    pub player: u8,
+    pub summonable: Vec<CardLocation>,
+    pub special_summonable: Vec<CardLocation>,
+    pub repositionable: Vec<CardLocation>,
+    pub monster_setable: Vec<CardLocation>,
+    pub spell_setable: Vec<CardLocation>,
+    pub activatable: Vec<ActivatableEffect>,
+    pub can_battle_phase: bool,
+    pub can_end_phase: bool,
+    pub can_shuffle: bool,
+}
+
+/// Prompt sent with [`MSG_SELECT_EFFECTYN`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SelectEffectYn {
+    pub player: u8,
+    pub card: CardInfo,
+    pub desc: u64,
+}
+
+/// Prompt sent with [`MSG_SELECT_YESNO`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SelectYesNo {
+    pub player: u8,
+    pub desc: u64,
+}
+
+/// Prompt sent with [`MSG_SELECT_OPTION`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SelectOption {
+    pub player: u8,
+    pub options: Vec<u64>,
+}
+
+/// Prompt sent with [`MSG_SELECT_CARD`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SelectCard {
+    pub player: u8,
+    pub cancelable: bool,
+    pub min: u32,
+    pub max: u32,
+    pub cards: Vec<CardInfo>,
+}
+
+/// Prompt sent with [`MSG_SELECT_CHAIN`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SelectChain {
+    pub player: u8,
+    pub spe_count: u8,
+    pub forced: bool,
+    pub hint_timing: u32,
+    pub other_timing: u32,
+    pub chains: Vec<ActivatableEffect>,
+}
+
+/// Prompt sent with [`MSG_SELECT_PLACE`] and [`MSG_SELECT_DISFIELD`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SelectPlace {
+    pub player: u8,
+    pub count: u8,
+    /// Bitmask of the zones that can NOT be selected.
+    /// The low 16 bits belong to `player`, the high 16 bits to their opponent;
+    /// in each half, bits 0-7 are monster zones and bits 8-15 spell/trap zones.
+    pub flag: u32,
+}
+
+/// Prompt sent with [`MSG_SELECT_POSITION`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SelectPosition {
+    pub player: u8,
+    pub code: u32,
+    /// Bitmask of the `POS_*` positions that can be chosen.
+    pub positions: u8,
+}
+
+/// Prompt sent with [`MSG_SELECT_TRIBUTE`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SelectTribute {
+    pub player: u8,
+    pub cancelable: bool,
+    pub min: u32,
+    pub max: u32,
+    pub cards: Vec<TributeCard>,
+}
+
+/// Prompt sent with [`MSG_SORT_CHAIN`] and [`MSG_SORT_CARD`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SortCard {
+    pub player: u8,
+    pub cards: Vec<CardLocation>,
+}
+
+/// Prompt sent with [`MSG_SELECT_COUNTER`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SelectCounter {
+    pub player: u8,
+    pub counter_type: u16,
+    /// The total number of counters that have to be removed.
+    pub count: u16,
+    pub cards: Vec<CounterCard>,
+}
+
+/// Prompt sent with [`MSG_SELECT_SUM`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SelectSum {
+    pub player: u8,
+    /// `0` if the sum has to be exactly `sum`, `1` if it has to be at least `sum`.
+    pub select_mode: u8,
+    pub sum: u32,
+    pub min: u32,
+    pub max: u32,
+    pub must_select: Vec<SumCard>,
+    pub selectable: Vec<SumCard>,
+}
+
+/// Prompt sent with [`MSG_SELECT_UNSELECT_CARD`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SelectUnselectCard {
+    pub player: u8,
+    pub finishable: bool,
+    pub cancelable: bool,
+    pub min: u32,
+    pub max: u32,
+    pub selectable: Vec<CardInfo>,
+    pub unselectable: Vec<CardInfo>,
+}
+
+/// Prompt sent with [`MSG_ANNOUNCE_RACE`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AnnounceRace {
+    pub player: u8,
+    pub count: u8,
+    /// Bitmask of the `RACE_*` values that can be announced.
+    pub available: u64,
+}
+
+/// Prompt sent with [`MSG_ANNOUNCE_ATTRIB`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AnnounceAttrib {
+    pub player: u8,
+    pub count: u8,
+    /// Bitmask of the `ATTRIBUTE_*` values that can be announced.
+    pub available: u32,
+}
+
+/// Prompt sent with [`MSG_ANNOUNCE_CARD`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AnnounceCard {
+    pub player: u8,
+    /// A reverse polish expression of card codes and `OPCODE_*` values
+    /// that an announced code has to satisfy.
+    pub opcodes: Vec<u64>,
+}
+
+/// Prompt sent with [`MSG_ANNOUNCE_NUMBER`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AnnounceNumber {
+    pub player: u8,
+    pub numbers: Vec<u64>,
+}
+
+/// Prompt sent with [`MSG_ROCK_PAPER_SCISSORS`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RockPaperScissors {
+    pub player: u8,
+}
+
+/// A single decoded record from the buffer returned by [`Duel::get_message`](crate::Duel::get_message).
+///
+/// Variants are named after the `MSG_*` constants in [`common`](crate::common).
+/// Messages the engine only emits for legacy clients keep their payload as raw bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Message {
+    Retry,
+    Hint {
+        hint_type: u8,
+        player: u8,
+        data: u64,
+    },
+    Waiting,
+    Start {
+        player_type: u8,
+        lp: [u32; 2],
+        deck_count: [u16; 2],
+        extra_count: [u16; 2],
+    },
+    Win {
+        player: u8,
+        reason: u8,
+    },
+    UpdateData {
+        player: u8,
+        loc: u32,
+        data: Vec<u8>,
+    },
+    UpdateCard {
+        player: u8,
+        loc: u32,
+        seq: u32,
+        data: Vec<u8>,
+    },
+    RequestDeck {
+        data: Vec<u8>,
+    },
+    SelectBattleCmd(SelectBattleCmd),
+    SelectIdleCmd(SelectIdleCmd),
+    SelectEffectYn(SelectEffectYn),
+    SelectYesNo(SelectYesNo),
+    SelectOption(SelectOption),
+    SelectCard(SelectCard),
+    SelectChain(SelectChain),
+    SelectPlace(SelectPlace),
+    SelectPosition(SelectPosition),
+    SelectTribute(SelectTribute),
+    SortChain(SortCard),
+    SelectCounter(SelectCounter),
+    SelectSum(SelectSum),
+    SelectDisfield(SelectPlace),
+    SortCard(SortCard),
+    SelectUnselectCard(SelectUnselectCard),
+    ConfirmDecktop {
+        player: u8,
+        cards: Vec<CardLocation>,
+    },
+    ConfirmCards {
+        player: u8,
+        cards: Vec<CardLocation>,
+    },
+    ShuffleDeck {
+        player: u8,
+    },
+    ShuffleHand {
+        player: u8,
+        codes: Vec<u32>,
+    },
+    RefreshDeck {
+        player: u8,
+    },
+    SwapGraveDeck {
+        player: u8,
+        deck_size: u32,
+        /// One bit per card of the new deck, set for cards that belong in the extra deck.
+        extra_bitfield: Vec<u8>,
+    },
+    ShuffleSetCard {
+        loc: u32,
+        previous: Vec<LocInfo>,
+        current: Vec<LocInfo>,
+    },
+    ReverseDeck,
+    DeckTop {
+        player: u8,
+        seq: u32,
+        code: u32,
+        pos: u32,
+    },
+    ShuffleExtra {
+        player: u8,
+        codes: Vec<u32>,
+    },
+    NewTurn {
+        player: u8,
+    },
+    NewPhase {
+        phase: u16,
+    },
+    ConfirmExtratop {
+        player: u8,
+        cards: Vec<CardLocation>,
+    },
+    Move {
+        code: u32,
+        previous: LocInfo,
+        current: LocInfo,
+        reason: u32,
+    },
+    PosChange {
+        code: u32,
+        con: u8,
+        loc: u32,
+        seq: u32,
+        previous_pos: u8,
+        current_pos: u8,
+    },
+    Set {
+        code: u32,
+        loc_info: LocInfo,
+    },
+    Swap {
+        first: CardInfo,
+        second: CardInfo,
+    },
+    FieldDisabled {
+        flag: u32,
+    },
+    Summoning {
+        code: u32,
+        loc_info: LocInfo,
+    },
+    Summoned,
+    SpSummoning {
+        code: u32,
+        loc_info: LocInfo,
+    },
+    SpSummoned,
+    FlipSummoning {
+        code: u32,
+        loc_info: LocInfo,
+    },
+    FlipSummoned,
+    Chaining {
+        code: u32,
+        loc_info: LocInfo,
+        triggering_con: u8,
+        triggering_loc: u32,
+        triggering_seq: u32,
+        desc: u64,
+        chain_count: u32,
+    },
+    Chained {
+        chain_count: u8,
+    },
+    ChainSolving {
+        chain_count: u8,
+    },
+    ChainSolved {
+        chain_count: u8,
+    },
+    ChainEnd,
+    ChainNegated {
+        chain_count: u8,
+    },
+    ChainDisabled {
+        chain_count: u8,
+    },
+    CardSelected {
+        cards: Vec<LocInfo>,
+    },
+    RandomSelected {
+        player: u8,
+        cards: Vec<LocInfo>,
+    },
+    BecomeTarget {
+        cards: Vec<LocInfo>,
+    },
+    Draw {
+        player: u8,
+        cards: Vec<CardCodePosition>,
+    },
+    Damage {
+        player: u8,
+        amount: u32,
+    },
+    Recover {
+        player: u8,
+        amount: u32,
+    },
+    Equip {
+        card: LocInfo,
+        target: LocInfo,
+    },
+    LpUpdate {
+        player: u8,
+        lp: u32,
+    },
+    Unequip {
+        card: LocInfo,
+    },
+    CardTarget {
+        card: LocInfo,
+        target: LocInfo,
+    },
+    CancelTarget {
+        card: LocInfo,
+        target: LocInfo,
+    },
+    PayLpCost {
+        player: u8,
+        amount: u32,
+    },
+    AddCounter {
+        counter_type: u16,
+        con: u8,
+        loc: u32,
+        seq: u32,
+        count: u16,
+    },
+    RemoveCounter {
+        counter_type: u16,
+        con: u8,
+        loc: u32,
+        seq: u32,
+        count: u16,
+    },
+    Attack {
+        attacker: LocInfo,
+        target: LocInfo,
+    },
+    Battle {
+        attacker: LocInfo,
+        attacker_attack: i32,
+        attacker_defense: i32,
+        attacker_destroyed: bool,
+        target: LocInfo,
+        target_attack: i32,
+        target_defense: i32,
+        target_destroyed: bool,
+    },
+    AttackDisabled,
+    DamageStepStart,
+    DamageStepEnd,
+    MissedEffect {
+        card: LocInfo,
+        code: u32,
+    },
+    BeChainTarget {
+        data: Vec<u8>,
+    },
+    CreateRelation {
+        data: Vec<u8>,
+    },
+    ReleaseRelation {
+        data: Vec<u8>,
+    },
+    TossCoin {
+        player: u8,
+        results: Vec<u8>,
+    },
+    TossDice {
+        player: u8,
+        results: Vec<u8>,
+    },
+    RockPaperScissors(RockPaperScissors),
+    HandRes {
+        results: u8,
+    },
+    AnnounceRace(AnnounceRace),
+    AnnounceAttrib(AnnounceAttrib),
+    AnnounceCard(AnnounceCard),
+    AnnounceNumber(AnnounceNumber),
+    CardHint {
+        card: LocInfo,
+        hint_type: u8,
+        value: u64,
+    },
+    TagSwap {
+        player: u8,
+        main_count: u32,
+        extra_count: u32,
+        extra_p_count: u32,
+        hand_count: u32,
+        top_code: u32,
+        hand: Vec<CardCodePosition>,
+        extra: Vec<CardCodePosition>,
+    },
+    ReloadField {
+        data: Vec<u8>,
+    },
+    AiName {
+        name: String,
+    },
+    ShowHint {
+        hint: String,
+    },
+    PlayerHint {
+        player: u8,
+        hint_type: u8,
+        value: u64,
+    },
+    MatchKill {
+        code: u32,
+    },
+    CustomMsg {
+        data: Vec<u8>,
+    },
+    RemoveCards {
+        cards: Vec<LocInfo>,
+    },
+    /// A message id this crate does not know about.
+    /// `bytes` is the body of the record, not including the id itself.
+    Unknown {
+        id: u8,
+        bytes: Vec<u8>,
+    },
+    /// A record of a known message id that could not be decoded,
+    /// kept by [`Duel::run_until_input`](crate::Duel::run_until_input) instead of failing.
+    /// `bytes` is the body of the record, not including the id itself.
+    Malformed {
+        id: u8,
+        bytes: Vec<u8>,
+    },
+}
+
+fn read_string(reader: &mut BufferReader) -> Result<String, DuelError> {
+    let len = reader.read_u16()? as usize;
+    let bytes = reader.read_bytes(len)?;
+    // Strings are written with a trailing NUL that is not included in the length.
+    if !reader.is_empty() {
+        reader.skip(1)?;
+    }
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+impl Message {
+    /// Decodes a single record, starting with its message id.
+    pub fn parse(record: &[u8]) -> Result<Message, DuelError> {
+        let mut reader = BufferReader::new(record);
+        let id = reader.read_u8()?;
+        Self::parse_body(id, &mut reader)
+    }
+    fn parse_body(id: u8, r: &mut BufferReader) -> Result<Message, DuelError> {
+        let message = match id {
+            MSG_RETRY => Message::Retry,
+            MSG_HINT => Message::Hint {
+                hint_type: r.read_u8()?,
+                player: r.read_u8()?,
+                data: r.read_u64()?,
+            },
+            MSG_WAITING => Message::Waiting,
+            MSG_START => {
+                let player_type = r.read_u8()?;
+                let lp = [r.read_u32()?, r.read_u32()?];
+                let (deck_0, extra_0) = (r.read_u16()?, r.read_u16()?);
+                let (deck_1, extra_1) = (r.read_u16()?, r.read_u16()?);
+                Message::Start {
+                    player_type,
+                    lp,
+                    deck_count: [deck_0, deck_1],
+                    extra_count: [extra_0, extra_1],
+                }
+            }
+            MSG_WIN => Message::Win {
+                player: r.read_u8()?,
+                reason: r.read_u8()?,
+            },
+            MSG_UPDATE_DATA => Message::UpdateData {
+                player: r.read_u8()?,
+                loc: r.read_u8()? as u32,
+                data: r.rest().to_vec(),
+            },
+            MSG_UPDATE_CARD => Message::UpdateCard {
+                player: r.read_u8()?,
+                loc: r.read_u8()? as u32,
+                seq: r.read_u8()? as u32,
+                data: r.rest().to_vec(),
+            },
+            MSG_REQUEST_DECK => Message::RequestDeck {
+                data: r.rest().to_vec(),
+            },
+            MSG_SELECT_BATTLECMD => {
+                let player = r.read_u8()?;
+                let count = r.read_u32()? as usize;
+                let activatable = r.read_vec(count, ActivatableEffect::read)?;
+                let count = r.read_u32()? as usize;
+                let attackable = r.read_vec(count, |r| {
+                    Ok(AttackableCard {
+                        card: CardLocation::read_short(r)?,
+                        direct_attackable: r.read_bool()?,
+                    })
+                })?;
+                Message::SelectBattleCmd(SelectBattleCmd {
+                    player,
+                    activatable,
+                    attackable,
+                    can_main2: r.read_bool()?,
+                    can_end_phase: r.read_bool()?,
+                })
+            }
+            MSG_SELECT_IDLECMD => {
+                let player = r.read_u8()?;
+                let count = r.read_u32()? as usize;
+                let summonable = r.read_vec(count, CardLocation::read)?;
+                let count = r.read_u32()? as usize;
+                let special_summonable = r.read_vec(count, CardLocation::read)?;
+                let count = r.read_u32()? as usize;
+                let repositionable = r.read_vec(count, CardLocation::read_short)?;
+                let count = r.read_u32()? as usize;
+                let monster_setable = r.read_vec(count, CardLocation::read)?;
+                let count = r.read_u32()? as usize;
+                let spell_setable = r.read_vec(count, CardLocation::read)?;
+                let count = r.read_u32()? as usize;
+                let activatable = r.read_vec(count, ActivatableEffect::read)?;
+                Message::SelectIdleCmd(SelectIdleCmd {
+                    player,
+                    summonable,
+                    special_summonable,
+                    repositionable,
+                    monster_setable,
+                    spell_setable,
+                    activatable,
+                    can_battle_phase: r.read_bool()?,
+                    can_end_phase: r.read_bool()?,
+                    can_shuffle: r.read_bool()?,
+                })
+            }
+            MSG_SELECT_EFFECTYN => Message::SelectEffectYn(SelectEffectYn {
+                player: r.read_u8()?,
+                card: CardInfo::read(r)?,
+                desc: r.read_u64()?,
+            }),
+            MSG_SELECT_YESNO => Message::SelectYesNo(SelectYesNo {
+                player: r.read_u8()?,
+                desc: r.read_u64()?,
+            }),
+            MSG_SELECT_OPTION => {
+                let player = r.read_u8()?;
+                let count = r.read_u8()? as usize;
+                Message::SelectOption(SelectOption {
+                    player,
+                    options: r.read_vec(count, |r| r.read_u64())?,
+                })
+            }
+            MSG_SELECT_CARD => {
+                let player = r.read_u8()?;
+                let cancelable = r.read_bool()?;
+                let min = r.read_u32()?;
+                let max = r.read_u32()?;
+                let count = r.read_u32()? as usize;
+                Message::SelectCard(SelectCard {
+                    player,
+                    cancelable,
+                    min,
+                    max,
+                    cards: r.read_vec(count, CardInfo::read)?,
+                })
+            }
+            MSG_SELECT_CHAIN => {
+                let player = r.read_u8()?;
+                let spe_count = r.read_u8()?;
+                let forced = r.read_bool()?;
+                let hint_timing = r.read_u32()?;
+                let other_timing = r.read_u32()?;
+                let count = r.read_u32()? as usize;
+                Message::SelectChain(SelectChain {
+                    player,
+                    spe_count,
+                    forced,
+                    hint_timing,
+                    other_timing,
+                    chains: r.read_vec(count, ActivatableEffect::read)?,
+                })
+            }
+            MSG_SELECT_PLACE | MSG_SELECT_DISFIELD => {
+                let place = SelectPlace {
+                    player: r.read_u8()?,
+                    count: r.read_u8()?,
+                    flag: r.read_u32()?,
+                };
+                if id == MSG_SELECT_PLACE {
+                    Message::SelectPlace(place)
+                } else {
+                    Message::SelectDisfield(place)
+                }
+            }
+            MSG_SELECT_POSITION => Message::SelectPosition(SelectPosition {
+                player: r.read_u8()?,
+                code: r.read_u32()?,
+                positions: r.read_u8()?,
+            }),
+            MSG_SELECT_TRIBUTE => {
+                let player = r.read_u8()?;
+                let cancelable = r.read_bool()?;
+                let min = r.read_u32()?;
+                let max = r.read_u32()?;
+                let count = r.read_u32()? as usize;
+                Message::SelectTribute(SelectTribute {
+                    player,
+                    cancelable,
+                    min,
+                    max,
+                    cards: r.read_vec(count, |r| {
+                        Ok(TributeCard {
+                            card: CardLocation::read(r)?,
+                            release_param: r.read_u8()?,
+                        })
+                    })?,
+                })
+            }
+            MSG_SORT_CHAIN | MSG_SORT_CARD => {
+                let player = r.read_u8()?;
+                let count = r.read_u32()? as usize;
+                let sort = SortCard {
+                    player,
+                    cards: r.read_vec(count, |r| {
+                        Ok(CardLocation {
+                            code: r.read_u32()?,
+                            con: r.read_u8()?,
+                            loc: r.read_u32()?,
+                            seq: r.read_u32()?,
+                        })
+                    })?,
+                };
+                if id == MSG_SORT_CHAIN {
+                    Message::SortChain(sort)
+                } else {
+                    Message::SortCard(sort)
+                }
+            }
+            MSG_SELECT_COUNTER => {
+                let player = r.read_u8()?;
+                let counter_type = r.read_u16()?;
+                let total = r.read_u16()?;
+                let count = r.read_u32()? as usize;
+                Message::SelectCounter(SelectCounter {
+                    player,
+                    counter_type,
+                    count: total,
+                    cards: r.read_vec(count, |r| {
+                        Ok(CounterCard {
+                            card: CardLocation::read_short(r)?,
+                            counter_count: r.read_u16()?,
+                        })
+                    })?,
+                })
+            }
+            MSG_SELECT_SUM => {
+                let player = r.read_u8()?;
+                let select_mode = r.read_u8()?;
+                let sum = r.read_u32()?;
+                let min = r.read_u32()?;
+                let max = r.read_u32()?;
+                let count = r.read_u32()? as usize;
+                let must_select = r.read_vec(count, SumCard::read)?;
+                let count = r.read_u32()? as usize;
+                let selectable = r.read_vec(count, SumCard::read)?;
+                Message::SelectSum(SelectSum {
+                    player,
+                    select_mode,
+                    sum,
+                    min,
+                    max,
+                    must_select,
+                    selectable,
+                })
+            }
+            MSG_SELECT_UNSELECT_CARD => {
+                let player = r.read_u8()?;
+                let finishable = r.read_bool()?;
+                let cancelable = r.read_bool()?;
+                let min = r.read_u32()?;
+                let max = r.read_u32()?;
+                let count = r.read_u32()? as usize;
+                let selectable = r.read_vec(count, CardInfo::read)?;
+                let count = r.read_u32()? as usize;
+                let unselectable = r.read_vec(count, CardInfo::read)?;
+                Message::SelectUnselectCard(SelectUnselectCard {
+                    player,
+                    finishable,
+                    cancelable,
+                    min,
+                    max,
+                    selectable,
+                    unselectable,
+                })
+            }
+            MSG_CONFIRM_DECKTOP | MSG_CONFIRM_CARDS | MSG_CONFIRM_EXTRATOP => {
+                let player = r.read_u8()?;
+                let count = r.read_u32()? as usize;
+                let cards = r.read_vec(count, CardLocation::read)?;
+                match id {
+                    MSG_CONFIRM_DECKTOP => Message::ConfirmDecktop { player, cards },
+                    MSG_CONFIRM_CARDS => Message::ConfirmCards { player, cards },
+                    _ => Message::ConfirmExtratop { player, cards },
+                }
+            }
+            MSG_SHUFFLE_DECK => Message::ShuffleDeck {
+                player: r.read_u8()?,
+            },
+            MSG_SHUFFLE_HAND | MSG_SHUFFLE_EXTRA => {
+                let player = r.read_u8()?;
+                let count = r.read_u32()? as usize;
+                let codes = r.read_vec(count, |r| r.read_u32())?;
+                if id == MSG_SHUFFLE_HAND {
+                    Message::ShuffleHand { player, codes }
+                } else {
+                    Message::ShuffleExtra { player, codes }
+                }
+            }
+            MSG_REFRESH_DECK => Message::RefreshDeck {
+                player: r.read_u8()?,
+            },
+            MSG_SWAP_GRAVE_DECK => Message::SwapGraveDeck {
+                player: r.read_u8()?,
+                deck_size: r.read_u32()?,
+                extra_bitfield: r.rest().to_vec(),
+            },
+            MSG_SHUFFLE_SET_CARD => {
+                let loc = r.read_u8()? as u32;
+                let count = r.read_u8()? as usize;
+                let previous = r.read_vec(count, LocInfo::read)?;
+                let current = r.read_vec(count, LocInfo::read)?;
+                Message::ShuffleSetCard {
+                    loc,
+                    previous,
+                    current,
+                }
+            }
+            MSG_REVERSE_DECK => Message::ReverseDeck,
+            MSG_DECK_TOP => Message::DeckTop {
+                player: r.read_u8()?,
+                seq: r.read_u32()?,
+                code: r.read_u32()?,
+                pos: r.read_u32()?,
+            },
+            MSG_NEW_TURN => Message::NewTurn {
+                player: r.read_u8()?,
+            },
+            MSG_NEW_PHASE => Message::NewPhase {
+                phase: r.read_u16()?,
+            },
+            MSG_MOVE => Message::Move {
+                code: r.read_u32()?,
+                previous: LocInfo::read(r)?,
+                current: LocInfo::read(r)?,
+                reason: r.read_u32()?,
+            },
+            MSG_POS_CHANGE => Message::PosChange {
+                code: r.read_u32()?,
+                con: r.read_u8()?,
+                loc: r.read_u8()? as u32,
+                seq: r.read_u8()? as u32,
+                previous_pos: r.read_u8()?,
+                current_pos: r.read_u8()?,
+            },
+            MSG_SET => Message::Set {
+                code: r.read_u32()?,
+                loc_info: LocInfo::read(r)?,
+            },
+            MSG_SWAP => Message::Swap {
+                first: CardInfo::read(r)?,
+                second: CardInfo::read(r)?,
+            },
+            MSG_FIELD_DISABLED => Message::FieldDisabled {
+                flag: r.read_u32()?,
+            },
+            MSG_SUMMONING => Message::Summoning {
+                code: r.read_u32()?,
+                loc_info: LocInfo::read(r)?,
+            },
+            MSG_SUMMONED => Message::Summoned,
+            MSG_SPSUMMONING => Message::SpSummoning {
+                code: r.read_u32()?,
+                loc_info: LocInfo::read(r)?,
+            },
+            MSG_SPSUMMONED => Message::SpSummoned,
+            MSG_FLIPSUMMONING => Message::FlipSummoning {
+                code: r.read_u32()?,
+                loc_info: LocInfo::read(r)?,
+            },
+            MSG_FLIPSUMMONED => Message::FlipSummoned,
+            MSG_CHAINING => Message::Chaining {
+                code: r.read_u32()?,
+                loc_info: LocInfo::read(r)?,
+                triggering_con: r.read_u8()?,
+                triggering_loc: r.read_u8()? as u32,
+                triggering_seq: r.read_u32()?,
+                desc: r.read_u64()?,
+                chain_count: r.read_u32()?,
+            },
+            MSG_CHAINED => Message::Chained {
+                chain_count: r.read_u8()?,
+            },
+            MSG_CHAIN_SOLVING => Message::ChainSolving {
+                chain_count: r.read_u8()?,
+            },
+            MSG_CHAIN_SOLVED => Message::ChainSolved {
+                chain_count: r.read_u8()?,
+            },
+            MSG_CHAIN_END => Message::ChainEnd,
+            MSG_CHAIN_NEGATED => Message::ChainNegated {
+                chain_count: r.read_u8()?,
+            },
+            MSG_CHAIN_DISABLED => Message::ChainDisabled {
+                chain_count: r.read_u8()?,
+            },
+            MSG_CARD_SELECTED | MSG_BECOME_TARGET | MSG_REMOVE_CARDS => {
+                let count = r.read_u32()? as usize;
+                let cards = r.read_vec(count, LocInfo::read)?;
+                match id {
+                    MSG_CARD_SELECTED => Message::CardSelected { cards },
+                    MSG_BECOME_TARGET => Message::BecomeTarget { cards },
+                    _ => Message::RemoveCards { cards },
+                }
+            }
+            MSG_RANDOM_SELECTED => {
+                let player = r.read_u8()?;
+                let count = r.read_u32()? as usize;
+                Message::RandomSelected {
+                    player,
+                    cards: r.read_vec(count, LocInfo::read)?,
+                }
+            }
+            MSG_DRAW => {
+                let player = r.read_u8()?;
+                let count = r.read_u32()? as usize;
+                Message::Draw {
+                    player,
+                    cards: r.read_vec(count, CardCodePosition::read)?,
+                }
+            }
+            MSG_DAMAGE => Message::Damage {
+                player: r.read_u8()?,
+                amount: r.read_u32()?,
+            },
+            MSG_RECOVER => Message::Recover {
+                player: r.read_u8()?,
+                amount: r.read_u32()?,
+            },
+            MSG_EQUIP => Message::Equip {
+                card: LocInfo::read(r)?,
+                target: LocInfo::read(r)?,
+            },
+            MSG_LPUPDATE => Message::LpUpdate {
+                player: r.read_u8()?,
+                lp: r.read_u32()?,
+            },
+            MSG_UNEQUIP => Message::Unequip {
+                card: LocInfo::read(r)?,
+            },
+            MSG_CARD_TARGET => Message::CardTarget {
+                card: LocInfo::read(r)?,
+                target: LocInfo::read(r)?,
+            },
+            MSG_CANCEL_TARGET => Message::CancelTarget {
+                card: LocInfo::read(r)?,
+                target: LocInfo::read(r)?,
+            },
+            MSG_PAY_LPCOST => Message::PayLpCost {
+                player: r.read_u8()?,
+                amount: r.read_u32()?,
+            },
+            MSG_ADD_COUNTER => Message::AddCounter {
+                counter_type: r.read_u16()?,
+                con: r.read_u8()?,
+                loc: r.read_u8()? as u32,
+                seq: r.read_u8()? as u32,
+                count: r.read_u16()?,
+            },
+            MSG_REMOVE_COUNTER => Message::RemoveCounter {
+                counter_type: r.read_u16()?,
+                con: r.read_u8()?,
+                loc: r.read_u8()? as u32,
+                seq: r.read_u8()? as u32,
+                count: r.read_u16()?,
+            },
+            MSG_ATTACK => Message::Attack {
+                attacker: LocInfo::read(r)?,
+                target: LocInfo::read(r)?,
+            },
+            MSG_BATTLE => Message::Battle {
+                attacker: LocInfo::read(r)?,
+                attacker_attack: r.read_i32()?,
+                attacker_defense: r.read_i32()?,
+                attacker_destroyed: r.read_bool()?,
+                target: LocInfo::read(r)?,
+                target_attack: r.read_i32()?,
+                target_defense: r.read_i32()?,
+                target_destroyed: r.read_bool()?,
+            },
+            MSG_ATTACK_DISABLED => Message::AttackDisabled,
+            MSG_DAMAGE_STEP_START => Message::DamageStepStart,
+            MSG_DAMAGE_STEP_END => Message::DamageStepEnd,
+            MSG_MISSED_EFFECT => Message::MissedEffect {
+                card: LocInfo::read(r)?,
+                code: r.read_u32()?,
+            },
+            MSG_BE_CHAIN_TARGET => Message::BeChainTarget {
+                data: r.rest().to_vec(),
+            },
+            MSG_CREATE_RELATION => Message::CreateRelation {
+                data: r.rest().to_vec(),
+            },
+            MSG_RELEASE_RELATION => Message::ReleaseRelation {
+                data: r.rest().to_vec(),
+            },
+            MSG_TOSS_COIN | MSG_TOSS_DICE => {
+                let player = r.read_u8()?;
+                let count = r.read_u8()? as usize;
+                let results = r.read_bytes(count)?.to_vec();
+                if id == MSG_TOSS_COIN {
+                    Message::TossCoin { player, results }
+                } else {
+                    Message::TossDice { player, results }
+                }
+            }
+            MSG_ROCK_PAPER_SCISSORS => Message::RockPaperScissors(RockPaperScissors {
+                player: r.read_u8()?,
+            }),
+            MSG_HAND_RES => Message::HandRes {
+                results: r.read_u8()?,
+            },
+            MSG_ANNOUNCE_RACE => Message::AnnounceRace(AnnounceRace {
+                player: r.read_u8()?,
+                count: r.read_u8()?,
+                available: r.read_u64()?,
+            }),
+            MSG_ANNOUNCE_ATTRIB => Message::AnnounceAttrib(AnnounceAttrib {
+                player: r.read_u8()?,
+                count: r.read_u8()?,
+                available: r.read_u32()?,
+            }),
+            MSG_ANNOUNCE_CARD => {
+                let player = r.read_u8()?;
+                let count = r.read_u8()? as usize;
+                Message::AnnounceCard(AnnounceCard {
+                    player,
+                    opcodes: r.read_vec(count, |r| r.read_u64())?,
+                })
+            }
+            MSG_ANNOUNCE_NUMBER => {
+                let player = r.read_u8()?;
+                let count = r.read_u8()? as usize;
+                Message::AnnounceNumber(AnnounceNumber {
+                    player,
+                    numbers: r.read_vec(count, |r| r.read_u64())?,
+                })
+            }
+            MSG_CARD_HINT => Message::CardHint {
+                card: LocInfo::read(r)?,
+                hint_type: r.read_u8()?,
+                value: r.read_u64()?,
+            },
+            MSG_TAG_SWAP => {
+                let player = r.read_u8()?;
+                let main_count = r.read_u32()?;
+                let extra_count = r.read_u32()?;
+                let extra_p_count = r.read_u32()?;
+                let hand_count = r.read_u32()?;
+                let top_code = r.read_u32()?;
+                let hand = r.read_vec(hand_count as usize, CardCodePosition::read)?;
+                let extra = r.read_vec(extra_count as usize, CardCodePosition::read)?;
+                Message::TagSwap {
+                    player,
+                    main_count,
+                    extra_count,
+                    extra_p_count,
+                    hand_count,
+                    top_code,
+                    hand,
+                    extra,
+                }
+            }
+            MSG_RELOAD_FIELD => Message::ReloadField {
+                data: r.rest().to_vec(),
+            },
+            MSG_AI_NAME => Message::AiName {
+                name: read_string(r)?,
+            },
+            MSG_SHOW_HINT => Message::ShowHint {
+                hint: read_string(r)?,
+            },
+            MSG_PLAYER_HINT => Message::PlayerHint {
+                player: r.read_u8()?,
+                hint_type: r.read_u8()?,
+                value: r.read_u64()?,
+            },
+            MSG_MATCH_KILL => Message::MatchKill {
+                code: r.read_u32()?,
+            },
+            MSG_CUSTOM_MSG => Message::CustomMsg {
+                data: r.rest().to_vec(),
+            },
+            _ => Message::Unknown {
+                id,
+                bytes: r.rest().to_vec(),
+            },
+        };
+        Ok(message)
+    }
+    /// Returns the `MSG_*` id of this message.
+    pub fn id(&self) -> u8 {
+        match self {
+            Message::Retry => MSG_RETRY,
+            Message::Hint { .. } => MSG_HINT,
+            Message::Waiting => MSG_WAITING,
+            Message::Start { .. } => MSG_START,
+            Message::Win { .. } => MSG_WIN,
+            Message::UpdateData { .. } => MSG_UPDATE_DATA,
+            Message::UpdateCard { .. } => MSG_UPDATE_CARD,
+            Message::RequestDeck { .. } => MSG_REQUEST_DECK,
+            Message::SelectBattleCmd(_) => MSG_SELECT_BATTLECMD,
+            Message::SelectIdleCmd(_) => MSG_SELECT_IDLECMD,
+            Message::SelectEffectYn(_) => MSG_SELECT_EFFECTYN,
+            Message::SelectYesNo(_) => MSG_SELECT_YESNO,
+            Message::SelectOption(_) => MSG_SELECT_OPTION,
+            Message::SelectCard(_) => MSG_SELECT_CARD,
+            Message::SelectChain(_) => MSG_SELECT_CHAIN,
+            Message::SelectPlace(_) => MSG_SELECT_PLACE,
+            Message::SelectPosition(_) => MSG_SELECT_POSITION,
+            Message::SelectTribute(_) => MSG_SELECT_TRIBUTE,
+            Message::SortChain(_) => MSG_SORT_CHAIN,
+            Message::SelectCounter(_) => MSG_SELECT_COUNTER,
+            Message::SelectSum(_) => MSG_SELECT_SUM,
+            Message::SelectDisfield(_) => MSG_SELECT_DISFIELD,
+            Message::SortCard(_) => MSG_SORT_CARD,
+            Message::SelectUnselectCard(_) => MSG_SELECT_UNSELECT_CARD,
+            Message::ConfirmDecktop { .. } => MSG_CONFIRM_DECKTOP,
+            Message::ConfirmCards { .. } => MSG_CONFIRM_CARDS,
+            Message::ShuffleDeck { .. } => MSG_SHUFFLE_DECK,
+            Message::ShuffleHand { .. } => MSG_SHUFFLE_HAND,
+            Message::RefreshDeck { .. } => MSG_REFRESH_DECK,
+            Message::SwapGraveDeck { .. } => MSG_SWAP_GRAVE_DECK,
+            Message::ShuffleSetCard { .. } => MSG_SHUFFLE_SET_CARD,
+            Message::ReverseDeck => MSG_REVERSE_DECK,
+            Message::DeckTop { .. } => MSG_DECK_TOP,
+            Message::ShuffleExtra { .. } => MSG_SHUFFLE_EXTRA,
+            Message::NewTurn { .. } => MSG_NEW_TURN,
+            Message::NewPhase { .. } => MSG_NEW_PHASE,
+            Message::ConfirmExtratop { .. } => MSG_CONFIRM_EXTRATOP,
+            Message::Move { .. } => MSG_MOVE,
+            Message::PosChange { .. } => MSG_POS_CHANGE,
+            Message::Set { .. } => MSG_SET,
+            Message::Swap { .. } => MSG_SWAP,
+            Message::FieldDisabled { .. } => MSG_FIELD_DISABLED,
+            Message::Summoning { .. } => MSG_SUMMONING,
+            Message::Summoned => MSG_SUMMONED,
+            Message::SpSummoning { .. } => MSG_SPSUMMONING,
+            Message::SpSummoned => MSG_SPSUMMONED,
+            Message::FlipSummoning { .. } => MSG_FLIPSUMMONING,
+            Message::FlipSummoned => MSG_FLIPSUMMONED,
+            Message::Chaining { .. } => MSG_CHAINING,
+            Message::Chained { .. } => MSG_CHAINED,
+            Message::ChainSolving { .. } => MSG_CHAIN_SOLVING,
+            Message::ChainSolved { .. } => MSG_CHAIN_SOLVED,
+            Message::ChainEnd => MSG_CHAIN_END,
+            Message::ChainNegated { .. } => MSG_CHAIN_NEGATED,
+            Message::ChainDisabled { .. } => MSG_CHAIN_DISABLED,
+            Message::CardSelected { .. } => MSG_CARD_SELECTED,
+            Message::RandomSelected { .. } => MSG_RANDOM_SELECTED,
+            Message::BecomeTarget { .. } => MSG_BECOME_TARGET,
+            Message::Draw { .. } => MSG_DRAW,
+            Message::Damage { .. } => MSG_DAMAGE,
+            Message::Recover { .. } => MSG_RECOVER,
+            Message::Equip { .. } => MSG_EQUIP,
+            Message::LpUpdate { .. } => MSG_LPUPDATE,
+            Message::Unequip { .. } => MSG_UNEQUIP,
+            Message::CardTarget { .. } => MSG_CARD_TARGET,
+            Message::CancelTarget { .. } => MSG_CANCEL_TARGET,
+            Message::PayLpCost { .. } => MSG_PAY_LPCOST,
+            Message::AddCounter { .. } => MSG_ADD_COUNTER,
+            Message::RemoveCounter { .. } => MSG_REMOVE_COUNTER,
+            Message::Attack { .. } => MSG_ATTACK,
+            Message::Battle { .. } => MSG_BATTLE,
+            Message::AttackDisabled => MSG_ATTACK_DISABLED,
+            Message::DamageStepStart => MSG_DAMAGE_STEP_START,
+            Message::DamageStepEnd => MSG_DAMAGE_STEP_END,
+            Message::MissedEffect { .. } => MSG_MISSED_EFFECT,
+            Message::BeChainTarget { .. } => MSG_BE_CHAIN_TARGET,
+            Message::CreateRelation { .. } => MSG_CREATE_RELATION,
+            Message::ReleaseRelation { .. } => MSG_RELEASE_RELATION,
+            Message::TossCoin { .. } => MSG_TOSS_COIN,
+            Message::TossDice { .. } => MSG_TOSS_DICE,
+            Message::RockPaperScissors(_) => MSG_ROCK_PAPER_SCISSORS,
+            Message::HandRes { .. } => MSG_HAND_RES,
+            Message::AnnounceRace(_) => MSG_ANNOUNCE_RACE,
+            Message::AnnounceAttrib(_) => MSG_ANNOUNCE_ATTRIB,
+            Message::AnnounceCard(_) => MSG_ANNOUNCE_CARD,
+            Message::AnnounceNumber(_) => MSG_ANNOUNCE_NUMBER,
+            Message::CardHint { .. } => MSG_CARD_HINT,
+            Message::TagSwap { .. } => MSG_TAG_SWAP,
+            Message::ReloadField { .. } => MSG_RELOAD_FIELD,
+            Message::AiName { .. } => MSG_AI_NAME,
+            Message::ShowHint { .. } => MSG_SHOW_HINT,
+            Message::PlayerHint { .. } => MSG_PLAYER_HINT,
+            Message::MatchKill { .. } => MSG_MATCH_KILL,
+            Message::CustomMsg { .. } => MSG_CUSTOM_MSG,
+            Message::RemoveCards { .. } => MSG_REMOVE_CARDS,
+            Message::Unknown { id, .. } | Message::Malformed { id, .. } => *id,
+        }
+    }
+}
+
+/// Iterator over the length-prefixed records of a [`Duel::get_message`](crate::Duel::get_message) buffer.
+///
+/// Each record is a `u32` length followed by that many bytes, the first of which is the message id.
+/// Iteration stops after the first record that fails to decode.
+#[derive(Debug, Clone)]
+pub struct Messages<'a> {
+    reader: BufferReader<'a>,
+}
+
+impl<'a> Messages<'a> {
+    pub fn new(buffer: &'a [u8]) -> Messages<'a> {
+        Messages {
+            reader: BufferReader::new(buffer),
+        }
+    }
+    fn next_record(&mut self) -> Result<&'a [u8], DuelError> {
+        let len = self.reader.read_u32()? as usize;
+        self.reader.read_bytes(len)
+    }
+}
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = Result<Message, DuelError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reader.is_empty() {
+            return None;
+        }
+        let result = self.next_record().and_then(Message::parse);
+        if result.is_err() {
+            self.reader.rest();
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(body: &[u8]) -> Vec<u8> {
+        let mut record = (body.len() as u32).to_le_bytes().to_vec();
+        record.extend_from_slice(body);
+        record
+    }
+
+    #[test]
+    fn test_parse_messages() {
+        let mut buffer = record(&[MSG_NEW_TURN, 1]);
+        let mut win = vec![MSG_WIN, 0, 4];
+        buffer.append(&mut record(&win));
+        let mut draw = vec![MSG_DRAW, 1];
+        draw.extend_from_slice(&1u32.to_le_bytes());
+        draw.extend_from_slice(&70095154u32.to_le_bytes());
+        draw.extend_from_slice(&(POS_FACEDOWN_DEFENSE as u32).to_le_bytes());
+        buffer.append(&mut record(&draw));
+        let messages: Vec<Message> = Messages::new(&buffer).map(Result::unwrap).collect();
+        assert_eq!(
+            messages,
+            vec![
+                Message::NewTurn { player: 1 },
+                Message::Win {
+                    player: 0,
+                    reason: 4
+                },
+                Message::Draw {
+                    player: 1,
+                    cards: vec![CardCodePosition {
+                        code: 70095154,
+                        pos: POS_FACEDOWN_DEFENSE as u32,
+                    }],
+                },
+            ]
+        );
+        win.truncate(2);
+        assert!(Message::parse(&win).is_err());
+    }
+    #[test]
+    fn test_parse_move_message() {
+        let mut body = vec![MSG_MOVE];
+        body.extend_from_slice(&70095154u32.to_le_bytes());
+        body.extend_from_slice(&[0, LOCATION_HAND as u8]);
+        body.extend_from_slice(&2u32.to_le_bytes());
+        body.extend_from_slice(&0u32.to_le_bytes());
+        body.extend_from_slice(&[0, LOCATION_MZONE as u8]);
+        body.extend_from_slice(&1u32.to_le_bytes());
+        body.extend_from_slice(&(POS_FACEUP_ATTACK as u32).to_le_bytes());
+        body.extend_from_slice(&REASON_SPSUMMON.to_le_bytes());
+        let message = Message::parse(&body).unwrap();
+        assert_eq!(message.id(), MSG_MOVE);
+        assert_eq!(
+            message,
+            Message::Move {
+                code: 70095154,
+                previous: LocInfo {
+                    con: 0,
+                    loc: LOCATION_HAND,
+                    seq: 2,
+                    pos: 0,
+                },
+                current: LocInfo {
+                    con: 0,
+                    loc: LOCATION_MZONE,
+                    seq: 1,
+                    pos: POS_FACEUP_ATTACK as u32,
+                },
+                reason: REASON_SPSUMMON,
+            }
+        );
+    }
+    #[test]
+    fn test_parse_unknown_message() {
+        let buffer = record(&[200, 1, 2, 3]);
+        let mut messages = Messages::new(&buffer);
+        assert_eq!(
+            messages.next().unwrap().unwrap(),
+            Message::Unknown {
+                id: 200,
+                bytes: vec![1, 2, 3]
+            }
+        );
+        assert!(messages.next().is_none());
+        // A length prefix pointing past the end of the buffer is an error, not a panic.
+        let mut messages = Messages::new(&[10, 0, 0, 0, MSG_RETRY]);
+        assert!(messages.next().unwrap().is_err());
+        assert!(messages.next().is_none());
+    }
+}
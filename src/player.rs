@@ -1,6 +1,6 @@
 use crate::ffi::OCG_Player;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Player {
     starting_lp: u32,
     starting_draw_count: u32,
@@ -17,6 +17,25 @@ impl Default for Player {
     }
 }
 
+impl Player {
+    pub fn new(starting_lp: u32, starting_draw_count: u32, draw_count_per_turn: u32) -> Player {
+        Player {
+            starting_lp,
+            starting_draw_count,
+            draw_count_per_turn,
+        }
+    }
+    pub fn starting_lp(&self) -> u32 {
+        self.starting_lp
+    }
+    pub fn starting_draw_count(&self) -> u32 {
+        self.starting_draw_count
+    }
+    pub fn draw_count_per_turn(&self) -> u32 {
+        self.draw_count_per_turn
+    }
+}
+
 impl From<Player> for OCG_Player {
     fn from(val: Player) -> Self {
         OCG_Player {
@@ -1,5 +1,10 @@
 use ffi::OCG_QueryInfo;
 
+use crate::buffer::BufferReader;
+use crate::common::*;
+use crate::error::DuelError;
+use crate::message::LocInfo;
+
 #[derive(Debug, Clone, Copy, Default)]
 pub struct QueryInfo {
     pub flags: u32,
@@ -20,3 +25,182 @@ impl From<QueryInfo> for OCG_QueryInfo {
         }
     }
 }
+
+/// A counter placed on a card, as reported by [`QUERY_COUNTERS`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Counter {
+    pub counter_type: u16,
+    pub count: u16,
+}
+
+/// A decoded card from the buffers returned by [`Duel::query`](crate::Duel::query)
+/// and [`Duel::query_location`](crate::Duel::query_location).
+///
+/// Only the fields whose `QUERY_*` flag was set in [`QueryInfo::flags`] are [`Some`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CardQuery {
+    pub code: Option<u32>,
+    pub position: Option<u32>,
+    pub alias: Option<u32>,
+    pub card_type: Option<u32>,
+    pub level: Option<u32>,
+    pub rank: Option<u32>,
+    pub attribute: Option<u32>,
+    pub race: Option<u64>,
+    pub attack: Option<i32>,
+    pub defense: Option<i32>,
+    pub base_attack: Option<i32>,
+    pub base_defense: Option<i32>,
+    pub reason: Option<u32>,
+    pub reason_card: Option<LocInfo>,
+    pub equip_card: Option<LocInfo>,
+    pub target_cards: Option<Vec<LocInfo>>,
+    /// The codes of the Xyz materials attached to this card.
+    pub overlay_cards: Option<Vec<u32>>,
+    pub counters: Option<Vec<Counter>>,
+    pub owner: Option<u8>,
+    pub status: Option<u32>,
+    pub is_public: Option<bool>,
+    pub lscale: Option<u32>,
+    pub rscale: Option<u32>,
+    pub link_rating: Option<u32>,
+    pub link_marker: Option<u32>,
+    pub is_hidden: Option<bool>,
+    pub cover: Option<u32>,
+}
+
+fn read_list<'a, T>(
+    reader: &mut BufferReader<'a>,
+    read: impl FnMut(&mut BufferReader<'a>) -> Result<T, DuelError>,
+) -> Result<Vec<T>, DuelError> {
+    let count = reader.read_u32()? as usize;
+    reader.read_vec(count, read)
+}
+
+impl CardQuery {
+    /// Decodes the buffer returned by [`Duel::query`](crate::Duel::query).
+    ///
+    /// Returns [`None`] if the buffer describes an empty slot.
+    pub fn parse(buffer: &[u8]) -> Result<Option<CardQuery>, DuelError> {
+        let mut reader = BufferReader::new(buffer);
+        if reader.is_empty() {
+            return Ok(None);
+        }
+        Self::read(&mut reader)
+    }
+    /// Decodes the buffer returned by [`Duel::query_location`](crate::Duel::query_location),
+    /// with one entry per slot of the location. Empty zones are [`None`].
+    pub fn parse_location(buffer: &[u8]) -> Result<Vec<Option<CardQuery>>, DuelError> {
+        let mut reader = BufferReader::new(buffer);
+        if reader.is_empty() {
+            return Ok(Vec::new());
+        }
+        let len = reader.read_u32()? as usize;
+        let mut reader = BufferReader::new(reader.read_bytes(len)?);
+        let mut cards = Vec::new();
+        while !reader.is_empty() {
+            cards.push(Self::read(&mut reader)?);
+        }
+        Ok(cards)
+    }
+    /// Reads the fields of one card up to and including [`QUERY_END`].
+    pub(crate) fn read(reader: &mut BufferReader) -> Result<Option<CardQuery>, DuelError> {
+        let mut query = CardQuery::default();
+        loop {
+            let size = reader.read_u16()? as usize;
+            if size == 0 {
+                return Ok(None);
+            }
+            let mut field = BufferReader::new(reader.read_bytes(size)?);
+            let flag = field.read_u32()?;
+            let r = &mut field;
+            match flag {
+                QUERY_END => return Ok(Some(query)),
+                QUERY_CODE => query.code = Some(r.read_u32()?),
+                QUERY_POSITION => query.position = Some(r.read_u32()?),
+                QUERY_ALIAS => query.alias = Some(r.read_u32()?),
+                QUERY_TYPE => query.card_type = Some(r.read_u32()?),
+                QUERY_LEVEL => query.level = Some(r.read_u32()?),
+                QUERY_RANK => query.rank = Some(r.read_u32()?),
+                QUERY_ATTRIBUTE => query.attribute = Some(r.read_u32()?),
+                QUERY_RACE => query.race = Some(r.read_u64()?),
+                QUERY_ATTACK => query.attack = Some(r.read_i32()?),
+                QUERY_DEFENSE => query.defense = Some(r.read_i32()?),
+                QUERY_BASE_ATTACK => query.base_attack = Some(r.read_i32()?),
+                QUERY_BASE_DEFENSE => query.base_defense = Some(r.read_i32()?),
+                QUERY_REASON => query.reason = Some(r.read_u32()?),
+                QUERY_REASON_CARD => query.reason_card = Some(LocInfo::read(r)?),
+                QUERY_EQUIP_CARD => query.equip_card = Some(LocInfo::read(r)?),
+                QUERY_TARGET_CARD => query.target_cards = Some(read_list(r, LocInfo::read)?),
+                QUERY_OVERLAY_CARD => query.overlay_cards = Some(read_list(r, |r| r.read_u32())?),
+                QUERY_COUNTERS => {
+                    query.counters = Some(read_list(r, |r| {
+                        let counter = r.read_u32()?;
+                        Ok(Counter {
+                            counter_type: (counter & 0xffff) as u16,
+                            count: (counter >> 16) as u16,
+                        })
+                    })?)
+                }
+                QUERY_OWNER => query.owner = Some(r.read_u8()?),
+                QUERY_STATUS => query.status = Some(r.read_u32()?),
+                QUERY_IS_PUBLIC => query.is_public = Some(r.read_bool()?),
+                QUERY_LSCALE => query.lscale = Some(r.read_u32()?),
+                QUERY_RSCALE => query.rscale = Some(r.read_u32()?),
+                QUERY_LINK => {
+                    query.link_rating = Some(r.read_u32()?);
+                    query.link_marker = Some(r.read_u32()?);
+                }
+                QUERY_IS_HIDDEN => query.is_hidden = Some(r.read_bool()?),
+                QUERY_COVER => query.cover = Some(r.read_u32()?),
+                // Every field is size prefixed, so flags added by newer cores can be skipped.
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(flag: u32, data: &[u8]) -> Vec<u8> {
+        let mut field = ((data.len() + 4) as u16).to_le_bytes().to_vec();
+        field.extend_from_slice(&flag.to_le_bytes());
+        field.extend_from_slice(data);
+        field
+    }
+
+    #[test]
+    fn test_parse_card_query() {
+        let mut buffer = field(QUERY_CODE, &70095154u32.to_le_bytes());
+        buffer.append(&mut field(QUERY_ATTACK, &2100i32.to_le_bytes()));
+        let mut overlay = 2u32.to_le_bytes().to_vec();
+        overlay.extend_from_slice(&1u32.to_le_bytes());
+        overlay.extend_from_slice(&2u32.to_le_bytes());
+        buffer.append(&mut field(QUERY_OVERLAY_CARD, &overlay));
+        buffer.append(&mut field(QUERY_END, &[]));
+        let query = CardQuery::parse(&buffer).unwrap().unwrap();
+        assert_eq!(query.code, Some(70095154));
+        assert_eq!(query.attack, Some(2100));
+        assert_eq!(query.overlay_cards, Some(vec![1, 2]));
+        assert_eq!(query.defense, None);
+    }
+    #[test]
+    fn test_parse_location_query() {
+        let mut cards = 0u16.to_le_bytes().to_vec();
+        cards.append(&mut field(QUERY_CODE, &1u32.to_le_bytes()));
+        cards.append(&mut field(QUERY_END, &[]));
+        cards.extend_from_slice(&0u16.to_le_bytes());
+        let mut buffer = (cards.len() as u32).to_le_bytes().to_vec();
+        buffer.append(&mut cards);
+        let queries = CardQuery::parse_location(&buffer).unwrap();
+        assert_eq!(queries.len(), 3);
+        assert!(queries[0].is_none());
+        assert_eq!(queries[1].as_ref().unwrap().code, Some(1));
+        assert!(queries[2].is_none());
+        assert!(CardQuery::parse_location(&buffer[..buffer.len() - 1]).is_err());
+    }
+}
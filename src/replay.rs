@@ -0,0 +1,1361 @@
+//! EDOPro replay files.
+//!
+//! A `.yrpX` file is an extended header followed by an LZMA compressed body holding
+//! the player names, the duel flags and every message the duel produced, one packet each.
+//! The last packet embeds the older `.yrp` format, which instead stores the decks and the responses,
+//! so the duel can be simulated again with a [`ReplayPlayer`].
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::rc::Rc;
+
+use crate::buffer::BufferReader;
+use crate::card::NewCardInfo;
+use crate::common::{LOCATION_DECK, LOCATION_EXTRA, POS_FACEDOWN_DEFENSE};
+use crate::duel::{Duel, DuelBuilder, DuelStatus};
+use crate::error::DuelError;
+use crate::field::FieldSnapshot;
+use crate::message::Message;
+use crate::player::Player;
+use crate::query::QueryInfo;
+use crate::response::Response;
+use crate::run::{DuelRun, RunOutcome};
+
+/// `yrp1`, a replay storing the decks and the responses.
+pub const REPLAY_YRP1: u32 = 0x31707279;
+/// `yrpX`, a replay storing the messages, with a `yrp1` replay embedded.
+pub const REPLAY_YRPX: u32 = 0x58707279;
+
+pub const REPLAY_COMPRESSED: u32 = 0x1;
+pub const REPLAY_TAG: u32 = 0x2;
+pub const REPLAY_DECODED: u32 = 0x4;
+pub const REPLAY_SINGLE_MODE: u32 = 0x8;
+pub const REPLAY_LUA64: u32 = 0x10;
+pub const REPLAY_NEWREPLAY: u32 = 0x20;
+pub const REPLAY_HAND_TEST: u32 = 0x40;
+pub const REPLAY_DIRECT_SEED: u32 = 0x80;
+pub const REPLAY_64BIT_DUELFLAG: u32 = 0x100;
+pub const REPLAY_EXTENDED_HEADER: u32 = 0x200;
+
+/// The packet of a `yrpX` replay holding the embedded `yrp1` replay.
+pub const OLD_REPLAY_MODE: u8 = 231;
+
+const EXTENDED_HEADER_VERSION: u64 = 1;
+/// The largest decompressed body read from a replay,
+/// far more than any duel needs but small enough that a crafted header cannot exhaust memory.
+const MAX_BODY_SIZE: u32 = 64 << 20;
+/// Player names are stored as 20 UTF-16 code units, including the terminating null.
+const NAME_LEN: usize = 20;
+
+/// Everything stored in a replay, as captured by a [`ReplayRecorder`] or read by [`Replay::open`].
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    pub seed: [u64; 4],
+    pub flags: u64,
+    pub team_1: Player,
+    pub team_2: Player,
+    /// The names of the duelists of each team, in turn order.
+    pub names: [Vec<String>; 2],
+    /// Every card passed to [`Duel::new_card`], in order.
+    pub cards: Vec<NewCardInfo>,
+    /// Every buffer passed to [`Duel::set_response`], in order.
+    pub responses: Vec<Vec<u8>>,
+    /// The [`Duel::state_hash`] of the duel when each response was given,
+    /// if the recorder was asked to [`record_hashes`](ReplayRecorder::set_record_hashes) at the time.
+    ///
+    /// Hashes are not part of the EDOPro format, so [`save`](#method.save) writes them
+    /// to a `.hashes` file next to the replay, which [`open`](#method.open) reads back:
+    /// a `u32` count followed, for each response, by a `u8` that is 1 if a `u64` hash follows and 0 otherwise.
+    pub hashes: Vec<Option<u64>>,
+    /// Every message returned by [`Duel::get_message`], each starting with its id.
+    pub messages: Vec<Vec<u8>>,
+}
+
+/// The part of the header needed to read the body of a replay.
+struct ReplayHeader {
+    id: u32,
+    flag: u32,
+    seed: [u64; 4],
+}
+
+impl Replay {
+    /// Serializes the replay as a `.yrpX` file.
+    ///
+    /// Returns an error if replays cannot store a card, as explained in [`ReplayRecorder::new_card`],
+    /// and [`DuelError::ReplayTeamMismatch`] as explained in [`to_yrp_bytes`](#method.to_yrp_bytes).
+    pub fn to_bytes(&self) -> Result<Vec<u8>, DuelError> {
+        let mut body = Vec::new();
+        self.write_names(&mut body);
+        body.extend_from_slice(&self.flags.to_le_bytes());
+        for message in &self.messages {
+            if let Some((&id, data)) = message.split_first() {
+                write_packet(&mut body, id, data);
+            }
+        }
+        write_packet(&mut body, OLD_REPLAY_MODE, &self.to_yrp_bytes()?);
+        self.write_file(REPLAY_YRPX, &body)
+    }
+    /// Serializes the replay as a `.yrp` file, which only stores the decks and the responses.
+    ///
+    /// `.yrp` files store a single starting LP, hand size and draw count,
+    /// so [`DuelError::ReplayTeamMismatch`] is returned unless both teams use the same ones.
+    pub fn to_yrp_bytes(&self) -> Result<Vec<u8>, DuelError> {
+        if self.team_1 != self.team_2 {
+            return Err(DuelError::ReplayTeamMismatch);
+        }
+        let mut previous = None;
+        for card in &self.cards {
+            check_card(previous, card)?;
+            previous = Some(card);
+        }
+        let mut body = Vec::new();
+        self.write_names(&mut body);
+        for value in [
+            self.team_1.starting_lp(),
+            self.team_1.starting_draw_count(),
+            self.team_1.draw_count_per_turn(),
+        ] {
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+        body.extend_from_slice(&self.flags.to_le_bytes());
+        for (team, duelists) in self.duelist_counts().into_iter().enumerate() {
+            for duelist in 0..duelists {
+                self.write_deck(&mut body, team as u8, duelist as u8);
+            }
+        }
+        for response in &self.responses {
+            body.extend_from_slice(&(response.len() as u32).to_le_bytes());
+            body.extend_from_slice(response);
+        }
+        self.write_file(REPLAY_YRP1, &body)
+    }
+    /// Writes the replay to a `.yrpX` file,
+    /// and its [`hashes`](#structfield.hashes) to a `.hashes` file next to it if any were recorded.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), DuelError> {
+        let path = path.as_ref();
+        fs::write(path, self.to_bytes()?)?;
+        let hashes_path = hashes_path(path);
+        if self.hashes.iter().all(Option::is_none) {
+            // Do not leave the hashes of a previous replay saved at the same path.
+            return match fs::remove_file(hashes_path) {
+                Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+                _ => Ok(()),
+            };
+        }
+        let mut file = (self.hashes.len() as u32).to_le_bytes().to_vec();
+        for hash in &self.hashes {
+            match hash {
+                Some(hash) => {
+                    file.push(1);
+                    file.extend_from_slice(&hash.to_le_bytes());
+                }
+                None => file.push(0),
+            }
+        }
+        Ok(fs::write(hashes_path, file)?)
+    }
+    /// Reads a `.yrpX` replay, or a legacy `.yrp` replay,
+    /// along with its [`hashes`](#structfield.hashes) if a `.hashes` file is next to it.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Replay, DuelError> {
+        let path = path.as_ref();
+        let mut replay = Replay::from_bytes(&fs::read(path)?)?;
+        let file = match fs::read(hashes_path(path)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(replay),
+            Err(err) => return Err(err.into()),
+        };
+        let invalid = |_| DuelError::InvalidReplay("the hashes are corrupted".to_owned());
+        let mut reader = BufferReader::new(&file);
+        let count = reader.read_u32().map_err(invalid)? as usize;
+        if count != replay.responses.len() {
+            return Err(DuelError::InvalidReplay(format!(
+                "{count} hashes were recorded for {} responses",
+                replay.responses.len()
+            )));
+        }
+        replay.hashes = reader
+            .read_vec(count, |reader| match reader.read_u8()? {
+                0 => Ok(None),
+                _ => reader.read_u64().map(Some),
+            })
+            .map_err(invalid)?;
+        Ok(replay)
+    }
+    /// Parses a `.yrpX` replay, or a legacy `.yrp` replay, without [`hashes`](#structfield.hashes).
+    ///
+    /// Returns [`DuelError::InvalidReplay`] if the file is not a replay or is corrupted.
+    /// Legacy replays without an extended header only store a 32 bit seed,
+    /// which becomes the first of the four seeds,
+    /// so replays recorded by older engines do not necessarily simulate the same duel again.
+    pub fn from_bytes(file: &[u8]) -> Result<Replay, DuelError> {
+        let (header, body) = read_file(file)?;
+        let mut reader = BufferReader::new(&body);
+        let mut replay = Replay {
+            seed: header.seed,
+            ..Default::default()
+        };
+        replay.read_names(&mut reader, header.flag)?;
+        match header.id {
+            REPLAY_YRPX => {
+                replay.flags = read_flags(&mut reader, header.flag)?;
+                let mut yrp = None;
+                while !reader.is_empty() {
+                    let id = reader.read_u8()?;
+                    let len = reader.read_u32()? as usize;
+                    let data = reader.read_bytes(len)?;
+                    if id == OLD_REPLAY_MODE {
+                        yrp = Some(Replay::read_embedded(data)?);
+                    } else {
+                        let mut message = vec![id];
+                        message.extend_from_slice(data);
+                        replay.messages.push(message);
+                    }
+                }
+                let yrp = yrp.ok_or_else(|| {
+                    DuelError::InvalidReplay("no .yrp replay is embedded".to_owned())
+                })?;
+                Ok(Replay {
+                    messages: replay.messages,
+                    ..yrp
+                })
+            }
+            REPLAY_YRP1 => {
+                replay.read_yrp(&mut reader, header.flag)?;
+                Ok(replay)
+            }
+            id => Err(DuelError::InvalidReplay(format!(
+                "unknown replay type {id:#x}"
+            ))),
+        }
+    }
+    /// Reads the `.yrp` replay embedded in a `.yrpX` replay, which cannot be another `.yrpX` replay.
+    fn read_embedded(file: &[u8]) -> Result<Replay, DuelError> {
+        let (header, body) = read_file(file)?;
+        if header.id != REPLAY_YRP1 {
+            return Err(DuelError::InvalidReplay(
+                "the embedded replay is not a .yrp replay".to_owned(),
+            ));
+        }
+        let mut reader = BufferReader::new(&body);
+        let mut replay = Replay {
+            seed: header.seed,
+            ..Default::default()
+        };
+        replay.read_names(&mut reader, header.flag)?;
+        replay.read_yrp(&mut reader, header.flag)?;
+        Ok(replay)
+    }
+    fn read_names(&mut self, reader: &mut BufferReader, flag: u32) -> Result<(), DuelError> {
+        if flag & REPLAY_SINGLE_MODE != 0 {
+            return Err(DuelError::InvalidReplay(
+                "single mode replays are not supported".to_owned(),
+            ));
+        }
+        for names in &mut self.names {
+            let count = if flag & REPLAY_NEWREPLAY != 0 {
+                reader.read_u32()? as usize
+            } else if flag & REPLAY_TAG != 0 {
+                2
+            } else {
+                1
+            };
+            *names = reader.read_vec(count, |reader| {
+                let units = reader.read_vec(NAME_LEN, BufferReader::read_u16)?;
+                let len = units.iter().position(|&unit| unit == 0).unwrap_or(NAME_LEN);
+                Ok(String::from_utf16_lossy(&units[..len]))
+            })?;
+        }
+        Ok(())
+    }
+    /// Reads the body of a `.yrp` replay following the names.
+    fn read_yrp(&mut self, reader: &mut BufferReader, flag: u32) -> Result<(), DuelError> {
+        // Hand test replays do not store the starting LP and draw counts, which are the defaults.
+        if flag & REPLAY_HAND_TEST == 0 {
+            let player = Player::new(reader.read_u32()?, reader.read_u32()?, reader.read_u32()?);
+            self.team_1 = player;
+            self.team_2 = player;
+        }
+        self.flags = read_flags(reader, flag)?;
+        for team in 0..2 {
+            for duelist in 0..self.names[team].len() {
+                for loc in [LOCATION_DECK, LOCATION_EXTRA] {
+                    let count = reader.read_u32()? as usize;
+                    let codes = reader.read_vec(count, BufferReader::read_u32)?;
+                    for code in codes.into_iter().rev() {
+                        self.cards.push(NewCardInfo {
+                            team: team as u8,
+                            duelist: duelist as u8,
+                            code,
+                            con: team as u8,
+                            loc,
+                            seq: 0,
+                            pos: POS_FACEDOWN_DEFENSE as u32,
+                        });
+                    }
+                }
+            }
+        }
+        while !reader.is_empty() {
+            // Legacy replays store the length of each response in a single byte.
+            let len = if flag & REPLAY_NEWREPLAY != 0 {
+                reader.read_u32()? as usize
+            } else {
+                reader.read_u8()? as usize
+            };
+            self.responses.push(reader.read_bytes(len)?.to_vec());
+        }
+        Ok(())
+    }
+    /// The number of duelists of each team, at least one,
+    /// and enough for every name and every card.
+    fn duelist_counts(&self) -> [usize; 2] {
+        [0, 1].map(|team| {
+            self.cards
+                .iter()
+                .filter(|card| card.team == team as u8)
+                .map(|card| card.duelist as usize + 1)
+                .chain([self.names[team].len(), 1])
+                .max()
+                .unwrap()
+        })
+    }
+    fn write_names(&self, body: &mut Vec<u8>) {
+        for (team, duelists) in self.duelist_counts().into_iter().enumerate() {
+            body.extend_from_slice(&(duelists as u32).to_le_bytes());
+            for duelist in 0..duelists {
+                let name = self.names[team].get(duelist).map_or("", String::as_str);
+                let mut units: Vec<u16> = name.encode_utf16().take(NAME_LEN - 1).collect();
+                units.resize(NAME_LEN, 0);
+                for unit in units {
+                    body.extend_from_slice(&unit.to_le_bytes());
+                }
+            }
+        }
+    }
+    fn write_deck(&self, body: &mut Vec<u8>, team: u8, duelist: u8) {
+        for loc in [LOCATION_DECK, LOCATION_EXTRA] {
+            let codes: Vec<u32> = self
+                .cards
+                .iter()
+                .filter(|card| card.team == team && card.duelist == duelist && card.loc == loc)
+                .map(|card| card.code)
+                .collect();
+            body.extend_from_slice(&(codes.len() as u32).to_le_bytes());
+            // EDOPro adds the cards of a deck starting from the last one stored.
+            for code in codes.into_iter().rev() {
+                body.extend_from_slice(&code.to_le_bytes());
+            }
+        }
+    }
+    /// Writes the extended header followed by the compressed body.
+    fn write_file(&self, id: u32, body: &[u8]) -> Result<Vec<u8>, DuelError> {
+        let mut compressed = Vec::new();
+        lzma_rs::lzma_compress_with_options(
+            &mut &body[..],
+            &mut compressed,
+            &lzma_rs::compress::Options {
+                unpacked_size: lzma_rs::compress::UnpackedSize::SkipWritingToHeader,
+            },
+        )?;
+        // The properties and dictionary size are stored in the header instead of the stream.
+        let data = compressed.split_off(5);
+        let [major, minor] = Duel::get_version();
+        let flag = REPLAY_COMPRESSED
+            | REPLAY_LUA64
+            | REPLAY_NEWREPLAY
+            | REPLAY_DIRECT_SEED
+            | REPLAY_64BIT_DUELFLAG
+            | REPLAY_EXTENDED_HEADER;
+        let mut file = Vec::with_capacity(72 + data.len());
+        for value in [
+            id,
+            ((major as u32) << 16) | minor as u32,
+            flag,
+            self.seed[0] as u32,
+            body.len() as u32,
+            0,
+        ] {
+            file.extend_from_slice(&value.to_le_bytes());
+        }
+        file.extend_from_slice(&compressed);
+        file.extend_from_slice(&[0; 3]);
+        file.extend_from_slice(&EXTENDED_HEADER_VERSION.to_le_bytes());
+        for seed in self.seed {
+            file.extend_from_slice(&seed.to_le_bytes());
+        }
+        file.extend_from_slice(&data);
+        Ok(file)
+    }
+}
+
+/// The order replays store cards in: by team, by duelist, then main deck before extra deck.
+fn card_group(card: &NewCardInfo) -> (u8, u8, bool) {
+    (card.team, card.duelist, card.loc == LOCATION_EXTRA)
+}
+
+/// Checks that a replay can store the card added after `previous`.
+fn check_card(previous: Option<&NewCardInfo>, card: &NewCardInfo) -> Result<(), DuelError> {
+    if card.loc != LOCATION_DECK && card.loc != LOCATION_EXTRA {
+        return Err(DuelError::ReplayCardLocation {
+            code: card.code,
+            loc: card.loc,
+        });
+    }
+    if card.team > 1
+        || card.con != card.team
+        || card.seq != 0
+        || card.pos != POS_FACEDOWN_DEFENSE as u32
+    {
+        return Err(DuelError::ReplayCardPlacement { code: card.code });
+    }
+    if previous.is_some_and(|previous| card_group(previous) > card_group(card)) {
+        return Err(DuelError::ReplayCardOrder { code: card.code });
+    }
+    Ok(())
+}
+
+/// The file next to a replay holding its [`Replay::hashes`].
+fn hashes_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".hashes");
+    path.into()
+}
+
+/// Reads the header and returns the decompressed body.
+fn read_file(file: &[u8]) -> Result<(ReplayHeader, Vec<u8>), DuelError> {
+    let invalid = |_| DuelError::InvalidReplay("the header is truncated".to_owned());
+    let mut reader = BufferReader::new(file);
+    let id = reader.read_u32().map_err(invalid)?;
+    let _version = reader.read_u32().map_err(invalid)?;
+    let flag = reader.read_u32().map_err(invalid)?;
+    let seed = reader.read_u32().map_err(invalid)?;
+    let size = reader.read_u32().map_err(invalid)?;
+    let _hash = reader.read_u32().map_err(invalid)?;
+    let props = reader.read_bytes(8).map_err(invalid)?;
+    let seed = if flag & REPLAY_EXTENDED_HEADER != 0 {
+        let _version = reader.read_u64().map_err(invalid)?;
+        let mut seeds = [0; 4];
+        for seed in &mut seeds {
+            *seed = reader.read_u64().map_err(invalid)?;
+        }
+        seeds
+    } else {
+        [seed as u64, 0, 0, 0]
+    };
+    let data = reader.rest();
+    let body = if flag & REPLAY_COMPRESSED != 0 {
+        if size > MAX_BODY_SIZE {
+            return Err(DuelError::InvalidReplay(format!(
+                "the body of {size} bytes is larger than {MAX_BODY_SIZE} bytes"
+            )));
+        }
+        let mut stream = props[..5].to_vec();
+        stream.extend_from_slice(data);
+        // The size comes from the file, so it is not trusted for preallocation.
+        let mut body = Vec::new();
+        lzma_rs::lzma_decompress_with_options(
+            &mut &stream[..],
+            &mut body,
+            &lzma_rs::decompress::Options {
+                unpacked_size: lzma_rs::decompress::UnpackedSize::UseProvided(Some(size as u64)),
+                ..Default::default()
+            },
+        )
+        .map_err(|err| DuelError::InvalidReplay(err.to_string()))?;
+        body
+    } else {
+        data.to_vec()
+    };
+    Ok((ReplayHeader { id, flag, seed }, body))
+}
+
+fn read_flags(reader: &mut BufferReader, flag: u32) -> Result<u64, DuelError> {
+    if flag & REPLAY_64BIT_DUELFLAG != 0 {
+        reader.read_u64()
+    } else {
+        Ok(reader.read_u32()? as u64)
+    }
+}
+
+fn write_packet(body: &mut Vec<u8>, id: u8, data: &[u8]) {
+    body.push(id);
+    body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    body.extend_from_slice(data);
+}
+
+/// Builds a duel with the seed, flags and players of the replay.
+fn build_duel(
+    replay: &Replay,
+    make_builder: &mut dyn FnMut() -> DuelBuilder,
+) -> Result<Pin<Box<Duel>>, DuelError> {
+    let mut builder = make_builder();
+    builder.set_seed(replay.seed);
+    builder.set_flags(replay.flags);
+    builder.set_team_1(replay.team_1);
+    builder.set_team_2(replay.team_2);
+    builder.try_build()
+}
+
+/// A call that changed the state of a recorded duel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Operation {
+    NewCard(NewCardInfo),
+    Start,
+    Process,
+    GetMessage,
+    SetResponse(Vec<u8>),
+}
+
+impl Operation {
+    fn apply(&self, duel: &Duel) {
+        match self {
+            Operation::NewCard(info) => duel.new_card(*info),
+            Operation::Start => duel.start(),
+            Operation::Process => {
+                duel.process();
+            }
+            Operation::GetMessage => {
+                // The messages were already recorded by the duel being forked.
+                duel.get_message();
+            }
+            Operation::SetResponse(response) => duel.set_response(response),
+        }
+    }
+}
+
+/// Duels released by forks, each with the operations that brought it to its state.
+struct ForkCache {
+    capacity: usize,
+    checkpoints: Vec<(Vec<Operation>, Pin<Box<Duel>>)>,
+}
+
+impl ForkCache {
+    /// Removes the checkpoint with the longest history that the provided history continues.
+    fn take(&mut self, history: &[Operation]) -> Option<(usize, Pin<Box<Duel>>)> {
+        let (index, _) = self
+            .checkpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, (operations, _))| history.starts_with(operations))
+            .max_by_key(|(_, (operations, _))| operations.len())?;
+        let (operations, duel) = self.checkpoints.remove(index);
+        Some((operations.len(), duel))
+    }
+    /// Adds a checkpoint, evicting the oldest one if the cache is full.
+    fn insert(&mut self, history: Vec<Operation>, duel: Pin<Box<Duel>>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.checkpoints.len() >= self.capacity {
+            self.checkpoints.remove(0);
+        }
+        self.checkpoints.push((history, duel));
+    }
+}
+
+/// What a recorder and all of its forks share.
+struct Forking {
+    make_builder: RefCell<Box<dyn FnMut() -> DuelBuilder>>,
+    cache: RefCell<ForkCache>,
+}
+
+impl fmt::Debug for Forking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Forking")
+            .field("checkpoints", &self.cache.borrow().checkpoints.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// How many released duels a [`ReplayRecorder`] and its forks keep by default.
+pub const DEFAULT_FORK_CACHE_CAPACITY: usize = 4;
+
+/// Creates a duel and records everything needed to write a replay of it.
+///
+/// Cards, responses and messages are only recorded when they go through the recorder,
+/// not through [`duel`](#method.duel).
+///
+/// A recorder created with [`forkable`](#method.forkable) can also [`fork`](#method.fork) its duel.
+#[derive(Debug)]
+pub struct ReplayRecorder {
+    duel: Pin<Box<Duel>>,
+    replay: Replay,
+    record_hashes: bool,
+    history: Vec<Operation>,
+    forking: Option<Rc<Forking>>,
+}
+
+impl ReplayRecorder {
+    /// Builds the duel, capturing the seed, flags and players of the builder.
+    pub fn new(builder: DuelBuilder) -> Result<ReplayRecorder, DuelError> {
+        let replay = Replay {
+            seed: builder.seed(),
+            flags: builder.flags(),
+            team_1: builder.team_1(),
+            team_2: builder.team_2(),
+            ..Default::default()
+        };
+        Ok(ReplayRecorder {
+            duel: builder.try_build()?,
+            replay,
+            record_hashes: false,
+            history: Vec::new(),
+            forking: None,
+        })
+    }
+    /// Builds the duel like [`new`](#method.new) with a builder returned by `make_builder`,
+    /// which is called again for every [`fork`](#method.fork) that cannot start from a checkpoint.
+    pub fn forkable<F>(mut make_builder: F) -> Result<ReplayRecorder, DuelError>
+    where
+        F: FnMut() -> DuelBuilder + 'static,
+    {
+        let mut recorder = ReplayRecorder::new(make_builder())?;
+        recorder.forking = Some(Rc::new(Forking {
+            make_builder: RefCell::new(Box::new(make_builder)),
+            cache: RefCell::new(ForkCache {
+                capacity: DEFAULT_FORK_CACHE_CAPACITY,
+                checkpoints: Vec::new(),
+            }),
+        }));
+        Ok(recorder)
+    }
+    /// Returns a recorder of a new duel in the same state as this one,
+    /// for example to explore what would happen after a response.
+    ///
+    /// The engine cannot copy a duel, so the new duel is built again and every card, `process` call and response is repeated.
+    /// It continues from the checkpoint with the longest matching history instead when one was [`release`](#method.release)d,
+    /// so forks of a duel that only advanced a little since the last released fork are cheap.
+    ///
+    /// Only the calls made through the recorder are repeated,
+    /// which is why it does not expose its duel for anything but queries.
+    ///
+    /// Returns [`DuelError::NotForkable`] if the recorder was not created with [`forkable`](#method.forkable).
+    pub fn fork(&self) -> Result<ReplayRecorder, DuelError> {
+        let forking = self.forking.as_ref().ok_or(DuelError::NotForkable)?;
+        let checkpoint = forking.cache.borrow_mut().take(&self.history);
+        let (done, duel) = match checkpoint {
+            Some(checkpoint) => checkpoint,
+            None => (
+                0,
+                build_duel(&self.replay, &mut *forking.make_builder.borrow_mut())?,
+            ),
+        };
+        for operation in &self.history[done..] {
+            operation.apply(&duel);
+        }
+        Ok(ReplayRecorder {
+            duel,
+            replay: self.replay.clone(),
+            record_hashes: self.record_hashes,
+            history: self.history.clone(),
+            forking: Some(forking.clone()),
+        })
+    }
+    /// Keeps the duel as a checkpoint that later forks of this recorder and its forks can continue from.
+    /// Does nothing if the recorder was not created with [`forkable`](#method.forkable).
+    pub fn release(self) {
+        if let Some(forking) = self.forking {
+            forking.cache.borrow_mut().insert(self.history, self.duel);
+        }
+    }
+    /// Sets how many released duels are kept, shared with every fork.
+    /// The oldest checkpoint is dropped when the cache is full.
+    pub fn set_fork_cache_capacity(&self, capacity: usize) {
+        if let Some(forking) = &self.forking {
+            let mut cache = forking.cache.borrow_mut();
+            cache.capacity = capacity;
+            let excess = cache.checkpoints.len().saturating_sub(capacity);
+            cache.checkpoints.drain(..excess);
+        }
+    }
+    /// See [`Duel::query_count`].
+    pub fn query_count(&self, team: u8, loc: u32) -> u32 {
+        self.duel.query_count(team, loc)
+    }
+    /// See [`Duel::query`].
+    pub fn query(&self, query_info: QueryInfo) -> Option<Vec<u8>> {
+        self.duel.query(query_info)
+    }
+    /// See [`Duel::query_location`].
+    pub fn query_location(&self, query_info: QueryInfo) -> Option<Vec<u8>> {
+        self.duel.query_location(query_info)
+    }
+    /// See [`Duel::query_field`].
+    pub fn query_field(&self) -> Option<Vec<u8>> {
+        self.duel.query_field()
+    }
+    /// See [`Duel::query_field_snapshot`].
+    pub fn query_field_snapshot(&self) -> Result<Option<FieldSnapshot>, DuelError> {
+        self.duel.query_field_snapshot()
+    }
+    /// See [`Duel::state_hash`].
+    pub fn state_hash(&self) -> u64 {
+        self.duel.state_hash()
+    }
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+    pub fn into_replay(self) -> Replay {
+        self.replay
+    }
+    /// Sets the names of the duelists of a team, 0 or 1, shown by the client.
+    pub fn set_names<S, I>(&mut self, team: u8, names: I)
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = S>,
+    {
+        self.replay.names[team as usize] = names.into_iter().map(Into::into).collect();
+    }
+    /// Records the [`Duel::state_hash`] along with every response,
+    /// so [`ReplayPlayer::verify`] can find where a simulation diverged.
+    /// Disabled by default, as hashing queries every location of both players.
+    pub fn set_record_hashes(&mut self, record_hashes: bool) {
+        self.record_hashes = record_hashes;
+    }
+    /// See [`Duel::new_card`].
+    ///
+    /// Replays only store the codes of each deck, so the card is not added if it cannot be read back the same way.
+    /// Returns [`DuelError::ReplayCardLocation`] if it is not added to a deck or an extra deck,
+    /// [`DuelError::ReplayCardPlacement`] if it is not face-down at sequence 0 and controlled by its team,
+    /// and [`DuelError::ReplayCardOrder`] if it is added after a card of a later team or duelist,
+    /// or a main deck card after an extra deck card of the same duelist.
+    pub fn new_card(&mut self, info: NewCardInfo) -> Result<(), DuelError> {
+        check_card(self.replay.cards.last(), &info)?;
+        self.duel.new_card(info);
+        self.replay.cards.push(info);
+        self.history.push(Operation::NewCard(info));
+        Ok(())
+    }
+    /// See [`Duel::start`].
+    pub fn start(&mut self) {
+        self.duel.start();
+        self.history.push(Operation::Start);
+    }
+    /// See [`Duel::process`].
+    pub fn process(&mut self) -> DuelStatus {
+        self.history.push(Operation::Process);
+        self.duel.process()
+    }
+    /// See [`Duel::get_message`].
+    pub fn get_message(&mut self) -> Vec<u8> {
+        let buffer = self.duel.get_message();
+        self.history.push(Operation::GetMessage);
+        let mut reader = BufferReader::new(&buffer);
+        while let Ok(len) = reader.read_u32() {
+            match reader.read_bytes(len as usize) {
+                Ok(message) => self.replay.messages.push(message.to_vec()),
+                Err(_) => break,
+            }
+        }
+        buffer
+    }
+    /// See [`Duel::set_response`].
+    pub fn set_response(&mut self, response: &[u8]) {
+        let hash = self.record_hashes.then(|| self.duel.state_hash());
+        self.replay.hashes.push(hash);
+        self.duel.set_response(response);
+        self.replay.responses.push(response.to_vec());
+        self.history.push(Operation::SetResponse(response.to_vec()));
+    }
+    /// See [`Duel::respond`].
+    pub fn respond(&mut self, response: &Response) {
+        self.set_response(&response.to_bytes());
+    }
+    /// Writes the replay recorded so far to a `.yrpX` file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), DuelError> {
+        self.replay.save(path)
+    }
+}
+
+/// The first response of a replay that was given in a different state than when it was recorded,
+/// as found by [`ReplayPlayer::verify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Desync {
+    pub response_index: usize,
+    /// The recorded hash, or [`None`] if no hash was recorded for the response.
+    pub expected: Option<u64>,
+    /// The hash of the simulated duel, or [`None`] if it ended before the response.
+    pub actual: Option<u64>,
+}
+
+/// Simulates the duel of a replay again, feeding it the recorded responses.
+///
+/// Iterating yields the messages produced between two responses,
+/// until the duel ends or the responses run out.
+pub struct ReplayPlayer {
+    make_builder: Box<dyn FnMut() -> DuelBuilder>,
+    replay: Replay,
+    duel: Pin<Box<Duel>>,
+    next_response: usize,
+    turn: u32,
+    /// The prompt [`seek_to_turn`](#method.seek_to_turn) stopped at, not answered yet.
+    pending: Option<RunOutcome>,
+    finished: bool,
+}
+
+impl ReplayPlayer {
+    /// Creates the duel, adds the cards of the replay and starts it.
+    ///
+    /// `make_builder` returns a builder with the card and script handlers set,
+    /// whose seed, flags and players are replaced by those of the replay.
+    /// It is called again every time the player rewinds.
+    pub fn new<F>(replay: Replay, mut make_builder: F) -> Result<ReplayPlayer, DuelError>
+    where
+        F: FnMut() -> DuelBuilder + 'static,
+    {
+        let duel = ReplayPlayer::start_duel(&replay, &mut make_builder)?;
+        Ok(ReplayPlayer {
+            make_builder: Box::new(make_builder),
+            replay,
+            duel,
+            next_response: 0,
+            turn: 0,
+            pending: None,
+            finished: false,
+        })
+    }
+    fn start_duel(
+        replay: &Replay,
+        make_builder: &mut dyn FnMut() -> DuelBuilder,
+    ) -> Result<Pin<Box<Duel>>, DuelError> {
+        let duel = build_duel(replay, make_builder)?;
+        for card in &replay.cards {
+            duel.new_card(*card);
+        }
+        duel.start();
+        Ok(duel)
+    }
+    pub fn duel(&self) -> &Duel {
+        &self.duel
+    }
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+    /// Returns the number of turns that have started so far.
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+    /// Returns the number of responses fed to the duel so far.
+    pub fn response_index(&self) -> usize {
+        self.next_response
+    }
+    /// Returns whether the duel ended or the responses ran out.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+    /// Processes the duel until it needs a response or ends,
+    /// then feeds it the next recorded response.
+    ///
+    /// Returns [`None`] once the player is finished.
+    pub fn step(&mut self) -> Result<Option<DuelRun>, DuelError> {
+        let Some(run) = self.run_until_input()? else {
+            return Ok(None);
+        };
+        self.feed_response(&run.outcome);
+        Ok(Some(run))
+    }
+    fn run_until_input(&mut self) -> Result<Option<DuelRun>, DuelError> {
+        if let Some(outcome) = self.pending.take() {
+            self.feed_response(&outcome);
+        }
+        if self.finished {
+            return Ok(None);
+        }
+        let run = self.duel.run_until_input().inspect_err(|_| {
+            self.finished = true;
+        })?;
+        self.turn += run
+            .messages
+            .iter()
+            .filter(|message| matches!(message, Message::NewTurn { .. }))
+            .count() as u32;
+        Ok(Some(run))
+    }
+    fn feed_response(&mut self, outcome: &RunOutcome) {
+        match (outcome, self.replay.responses.get(self.next_response)) {
+            (RunOutcome::Awaiting(_) | RunOutcome::Retry, Some(response)) => {
+                self.duel.set_response(response);
+                self.next_response += 1;
+            }
+            _ => self.finished = true,
+        }
+    }
+    /// Plays the rest of the replay, comparing the [`Duel::state_hash`] before each response
+    /// with the one recorded in [`Replay::hashes`].
+    ///
+    /// Returns the first response given in a different state,
+    /// or that could not be given because the duel ended early.
+    pub fn verify(&mut self) -> Result<Option<Desync>, DuelError> {
+        loop {
+            let outcome = match self.pending.take() {
+                Some(outcome) => outcome,
+                None => match self.run_until_input()? {
+                    Some(run) => run.outcome,
+                    None => break,
+                },
+            };
+            let index = self.next_response;
+            if let RunOutcome::Awaiting(_) | RunOutcome::Retry = outcome {
+                if let Some(&Some(expected)) = self.replay.hashes.get(index) {
+                    let actual = self.duel.state_hash();
+                    if actual != expected {
+                        self.finished = true;
+                        return Ok(Some(Desync {
+                            response_index: index,
+                            expected: Some(expected),
+                            actual: Some(actual),
+                        }));
+                    }
+                }
+            }
+            self.feed_response(&outcome);
+        }
+        if self.next_response < self.replay.responses.len() {
+            return Ok(Some(Desync {
+                response_index: self.next_response,
+                expected: self
+                    .replay
+                    .hashes
+                    .get(self.next_response)
+                    .copied()
+                    .flatten(),
+                actual: None,
+            }));
+        }
+        Ok(None)
+    }
+    /// Starts the duel over from the beginning.
+    pub fn rewind(&mut self) -> Result<(), DuelError> {
+        self.duel = ReplayPlayer::start_duel(&self.replay, &mut self.make_builder)?;
+        self.next_response = 0;
+        self.turn = 0;
+        self.pending = None;
+        self.finished = false;
+        Ok(())
+    }
+    /// Plays the replay until the first prompt once the turn with the provided number has started,
+    /// rewinding first unless that turn is still to come.
+    ///
+    /// Returns the run that reached the prompt, or [`None`] if the player finished before.
+    /// The prompt is answered by the next call to [`step`](#method.step),
+    /// so the duel can be inspected in the meantime.
+    pub fn seek_to_turn(&mut self, turn: u32) -> Result<Option<DuelRun>, DuelError> {
+        if turn <= self.turn {
+            self.rewind()?;
+        }
+        while let Some(run) = self.run_until_input()? {
+            if self.turn >= turn {
+                if let RunOutcome::Awaiting(_) | RunOutcome::Retry = run.outcome {
+                    self.pending = Some(run.outcome.clone());
+                    return Ok(Some(run));
+                }
+            }
+            self.feed_response(&run.outcome);
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for ReplayPlayer {
+    type Item = Result<DuelRun, DuelError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step().transpose()
+    }
+}
+
+/// Records a duel without cards until its first prompt, where the first player picks a main phase action.
+/// Ending their turn then ends the duel, as the second player cannot draw.
+#[cfg(test)]
+pub(crate) fn record_duel_without_cards() -> ReplayRecorder {
+    let mut recorder = ReplayRecorder::new(DuelBuilder::new()).unwrap();
+    recorder.start();
+    recorder.process_until_input();
+    recorder
+}
+
+#[cfg(test)]
+impl ReplayRecorder {
+    /// Processes the duel until it needs a response or ends, recording every message.
+    pub(crate) fn process_until_input(&mut self) {
+        loop {
+            let status = self.process();
+            self.get_message();
+            if !matches!(status, DuelStatus::Continue) {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{POS_FACEDOWN_DEFENSE, POS_FACEUP_ATTACK};
+
+    fn decompress(file: &[u8]) -> Vec<u8> {
+        let size = u32::from_le_bytes(file[16..20].try_into().unwrap());
+        let mut stream = file[24..29].to_vec();
+        stream.extend_from_slice(&file[72..]);
+        let mut body = Vec::new();
+        lzma_rs::lzma_decompress_with_options(
+            &mut &stream[..],
+            &mut body,
+            &lzma_rs::decompress::Options {
+                unpacked_size: lzma_rs::decompress::UnpackedSize::UseProvided(Some(size as u64)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        body
+    }
+
+    #[test]
+    fn test_replay_recorder() {
+        let mut builder = DuelBuilder::new();
+        builder.set_seed([1, 2, 3, 4]);
+        let mut recorder = ReplayRecorder::new(builder).unwrap();
+        recorder.set_names(0, ["Yugi"]);
+        recorder
+            .new_card(NewCardInfo {
+                code: 70095154,
+                loc: LOCATION_DECK,
+                pos: POS_FACEDOWN_DEFENSE as u32,
+                ..Default::default()
+            })
+            .unwrap();
+        recorder.start();
+        recorder.process();
+        recorder.get_message();
+        recorder.set_response(&[1, 0, 0, 0]);
+        let file = recorder.replay().to_bytes().unwrap();
+        assert_eq!(&file[..4], b"yrpX");
+        assert_eq!(&file[48..56], &2u64.to_le_bytes());
+        let body = decompress(&file);
+        assert_eq!(&body[..4], &1u32.to_le_bytes());
+        assert_eq!(&body[4..12], b"Y\0u\0g\0i\0");
+        // Skip the names and the flags, then find the embedded replay among the packets.
+        let mut reader = BufferReader::new(&body[96..]);
+        let yrp = loop {
+            let id = reader.read_u8().unwrap();
+            let len = reader.read_u32().unwrap() as usize;
+            let data = reader.read_bytes(len).unwrap();
+            if id == OLD_REPLAY_MODE {
+                break data;
+            }
+        };
+        assert_eq!(&yrp[..4], b"yrp1");
+        let yrp_body = decompress(yrp);
+        assert!(yrp_body.ends_with(&[4, 0, 0, 0, 1, 0, 0, 0]));
+
+        let mut replay = recorder.into_replay();
+        replay.cards[0].loc = crate::common::LOCATION_MZONE;
+        replay.cards[0].pos = POS_FACEUP_ATTACK as u32;
+        assert!(matches!(
+            replay.to_bytes(),
+            Err(DuelError::ReplayCardLocation { code: 70095154, .. })
+        ));
+    }
+    #[test]
+    fn test_yrp_file_layout() {
+        let replay = Replay {
+            seed: [1, 2, 3, 4],
+            flags: 0x10,
+            names: [vec!["A".into()], vec![]],
+            cards: vec![NewCardInfo {
+                team: 1,
+                code: 70095154,
+                con: 1,
+                loc: LOCATION_EXTRA,
+                pos: POS_FACEDOWN_DEFENSE as u32,
+                ..Default::default()
+            }],
+            responses: vec![vec![7, 8]],
+            ..Default::default()
+        };
+        let file = replay.to_yrp_bytes().unwrap();
+        let [major, minor] = Duel::get_version();
+        let body = decompress(&file);
+        let mut header = Vec::new();
+        for value in [
+            REPLAY_YRP1,
+            ((major as u32) << 16) | minor as u32,
+            0x3b1,
+            1,
+            body.len() as u32,
+            0,
+        ] {
+            header.extend_from_slice(&value.to_le_bytes());
+        }
+        assert_eq!(&file[..24], &header[..]);
+        assert_eq!(&file[29..32], &[0; 3]);
+        assert_eq!(&file[32..40], &1u64.to_le_bytes());
+        assert_eq!(
+            &file[40..72],
+            &[1u64, 2, 3, 4].map(u64::to_le_bytes).concat()[..]
+        );
+
+        let mut expected = 1u32.to_le_bytes().to_vec();
+        expected.extend_from_slice(b"A\0");
+        expected.extend_from_slice(&[0; 38]);
+        expected.extend_from_slice(&1u32.to_le_bytes());
+        expected.extend_from_slice(&[0; 40]);
+        for value in [8000u32, 5, 1] {
+            expected.extend_from_slice(&value.to_le_bytes());
+        }
+        expected.extend_from_slice(&0x10u64.to_le_bytes());
+        // Team 0 has an empty deck and extra deck, team 1 a single extra deck card.
+        for value in [0u32, 0, 0, 1, 70095154, 2] {
+            expected.extend_from_slice(&value.to_le_bytes());
+        }
+        expected.extend_from_slice(&[7, 8]);
+        assert_eq!(body, expected);
+
+        let mismatched = Replay {
+            team_2: Player::new(4000, 5, 1),
+            ..replay
+        };
+        assert!(matches!(
+            mismatched.to_bytes(),
+            Err(DuelError::ReplayTeamMismatch)
+        ));
+    }
+    #[test]
+    fn test_replay_card_order() {
+        let card = |team, duelist, loc, code| NewCardInfo {
+            team,
+            duelist,
+            code,
+            con: team,
+            loc,
+            pos: POS_FACEDOWN_DEFENSE as u32,
+            ..Default::default()
+        };
+        let cards = [
+            card(0, 0, LOCATION_DECK, 1),
+            card(0, 0, LOCATION_DECK, 2),
+            card(0, 0, LOCATION_EXTRA, 3),
+            card(0, 1, LOCATION_DECK, 4),
+            card(1, 0, LOCATION_DECK, 5),
+            card(1, 0, LOCATION_EXTRA, 6),
+        ];
+        let mut recorder = ReplayRecorder::new(DuelBuilder::new()).unwrap();
+        for card in cards {
+            recorder.new_card(card).unwrap();
+        }
+        // Both extra decks first, then a main deck.
+        let mut interleaved = ReplayRecorder::new(DuelBuilder::new()).unwrap();
+        interleaved.new_card(cards[2]).unwrap();
+        interleaved.new_card(cards[5]).unwrap();
+        assert!(matches!(
+            interleaved.new_card(cards[0]),
+            Err(DuelError::ReplayCardOrder { code: 1 })
+        ));
+        assert!(matches!(
+            interleaved.new_card(NewCardInfo { con: 0, ..cards[5] }),
+            Err(DuelError::ReplayCardPlacement { code: 6 })
+        ));
+        assert_eq!(interleaved.replay().cards, [cards[2], cards[5]]);
+
+        let file = recorder.replay().to_yrp_bytes().unwrap();
+        // The main deck of the first duelist is stored from the last card added.
+        let body = decompress(&file);
+        let deck = 8 + 2 * NAME_LEN * 3 + 12 + 8;
+        assert_eq!(
+            &body[deck..deck + 12],
+            &[2u32, 2, 1].map(u32::to_le_bytes).concat()[..]
+        );
+        assert_eq!(Replay::from_bytes(&file).unwrap().cards, cards);
+    }
+    #[test]
+    fn test_replay_player() {
+        let mut builder = DuelBuilder::new();
+        builder.set_seed([5, 6, 7, 8]);
+        builder.set_flags(crate::common::DUEL_MODE_MR5);
+        let mut recorder = ReplayRecorder::new(builder).unwrap();
+        recorder.set_names(1, ["Kaiba", "Mokuba"]);
+        recorder
+            .new_card(NewCardInfo {
+                team: 1,
+                duelist: 1,
+                code: 89631139,
+                con: 1,
+                loc: LOCATION_EXTRA,
+                pos: POS_FACEDOWN_DEFENSE as u32,
+                ..Default::default()
+            })
+            .unwrap();
+        recorder.set_response(&[0; 8]);
+        let replay = Replay::from_bytes(&recorder.replay().to_bytes().unwrap()).unwrap();
+        assert_eq!(replay.seed, [5, 6, 7, 8]);
+        assert_eq!(replay.flags, crate::common::DUEL_MODE_MR5);
+        assert_eq!(
+            replay.names,
+            [vec![String::new()], vec!["Kaiba".into(), "Mokuba".into()]]
+        );
+        assert_eq!(replay.cards.len(), 1);
+        assert_eq!(
+            (replay.cards[0].duelist, replay.cards[0].code),
+            (1, 89631139)
+        );
+        assert_eq!(replay.responses, [vec![0; 8]]);
+        assert!(matches!(
+            Replay::from_bytes(b"yrpX"),
+            Err(DuelError::InvalidReplay(_))
+        ));
+        let mut file = recorder.replay().to_bytes().unwrap();
+        file[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            Replay::from_bytes(&file),
+            Err(DuelError::InvalidReplay(_))
+        ));
+        // An uncompressed hand test replay, which does not store the starting LP and draw counts.
+        let yrp = replay.to_yrp_bytes().unwrap();
+        let mut body = decompress(&yrp);
+        let names = 8 + 2 * NAME_LEN * 3;
+        body.drain(names..names + 12);
+        let mut file = Vec::new();
+        for value in [
+            REPLAY_YRP1,
+            0,
+            REPLAY_NEWREPLAY | REPLAY_HAND_TEST | REPLAY_64BIT_DUELFLAG,
+            5,
+            body.len() as u32,
+            0,
+            0,
+            0,
+        ] {
+            file.extend_from_slice(&value.to_le_bytes());
+        }
+        file.extend_from_slice(&body);
+        let hand_test = Replay::from_bytes(&file).unwrap();
+        assert_eq!(hand_test.team_1, Player::default());
+        assert_eq!(hand_test.flags, crate::common::DUEL_MODE_MR5);
+        assert_eq!(hand_test.cards, replay.cards);
+        assert_eq!(hand_test.responses, replay.responses);
+        // A `.yrpX` replay embedding another `.yrpX` replay is rejected.
+        let mut body = Vec::new();
+        replay.write_names(&mut body);
+        body.extend_from_slice(&replay.flags.to_le_bytes());
+        write_packet(&mut body, OLD_REPLAY_MODE, &replay.to_bytes().unwrap());
+        assert!(matches!(
+            Replay::from_bytes(&replay.write_file(REPLAY_YRPX, &body).unwrap()),
+            Err(DuelError::InvalidReplay(_))
+        ));
+
+        let mut player = ReplayPlayer::new(replay, DuelBuilder::new).unwrap();
+        while player.step().unwrap().is_some() {}
+        assert!(player.is_finished());
+        player.rewind().unwrap();
+        assert_eq!(player.response_index(), 0);
+        assert!(player.all(|run| run.is_ok()));
+    }
+    #[test]
+    fn test_seek_to_turn() {
+        use crate::response::IdleCmdAction;
+
+        let mut recorder = record_duel_without_cards();
+        recorder.respond(&Response::SelectIdleCmd(IdleCmdAction::EndPhase));
+        let mut player = ReplayPlayer::new(recorder.into_replay(), DuelBuilder::new).unwrap();
+        let prompt = player
+            .seek_to_turn(1)
+            .unwrap()
+            .expect("the first turn has no prompt");
+        assert!(matches!(
+            prompt.outcome,
+            RunOutcome::Awaiting(Message::SelectIdleCmd(_))
+        ));
+        assert_eq!((player.turn(), player.response_index()), (1, 0));
+        let run = player.step().unwrap().unwrap();
+        assert_eq!(player.response_index(), 1);
+        assert!(matches!(run.outcome, RunOutcome::Ended(Some(_))));
+        assert!(player.is_finished());
+        // Seeking to a turn that has passed starts over.
+        assert_eq!(player.seek_to_turn(1).unwrap(), Some(prompt));
+        assert_eq!(player.seek_to_turn(2).unwrap(), None);
+    }
+    #[test]
+    fn test_verify_replay() {
+        use crate::response::IdleCmdAction;
+
+        let mut recorder = record_duel_without_cards();
+        recorder.set_record_hashes(true);
+        let hash = recorder.state_hash();
+        recorder.respond(&Response::SelectIdleCmd(IdleCmdAction::EndPhase));
+        assert_eq!(recorder.replay().hashes, [Some(hash)]);
+
+        let path = std::env::temp_dir().join(format!("ygopro-verify-{}.yrpX", std::process::id()));
+        recorder.save(&path).unwrap();
+        let replay = Replay::open(&path).unwrap();
+        // Change the saved hash of the first response.
+        let mut hashes = fs::read(hashes_path(&path)).unwrap();
+        hashes[5] ^= 1;
+        fs::write(hashes_path(&path), hashes).unwrap();
+        let changed = Replay::open(&path);
+        fs::remove_file(hashes_path(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(replay.hashes, [Some(hash)]);
+        let mut player = ReplayPlayer::new(replay, DuelBuilder::new).unwrap();
+        assert_eq!(player.verify().unwrap(), None);
+        let mut player = ReplayPlayer::new(changed.unwrap(), DuelBuilder::new).unwrap();
+        assert_eq!(
+            player.verify().unwrap(),
+            Some(Desync {
+                response_index: 0,
+                expected: Some(hash ^ 1),
+                actual: Some(hash)
+            })
+        );
+    }
+    #[test]
+    fn test_fork_recorder() {
+        use std::cell::Cell;
+
+        let builds = Rc::new(Cell::new(0));
+        let counter = builds.clone();
+        let mut recorder = ReplayRecorder::forkable(move || {
+            counter.set(counter.get() + 1);
+            let mut builder = DuelBuilder::new();
+            builder.set_seed([9, 9, 9, 9]);
+            builder
+        })
+        .unwrap();
+        recorder
+            .new_card(NewCardInfo {
+                code: 70095154,
+                loc: LOCATION_DECK,
+                pos: POS_FACEDOWN_DEFENSE as u32,
+                ..Default::default()
+            })
+            .unwrap();
+        recorder.start();
+        recorder.process();
+        recorder.set_response(&[0; 4]);
+
+        let fork = recorder.fork().unwrap();
+        assert_eq!(builds.get(), 2);
+        assert_eq!(fork.replay().seed, [9, 9, 9, 9]);
+        assert_eq!(fork.replay().responses, recorder.replay().responses);
+        assert_eq!(fork.state_hash(), recorder.state_hash());
+        fork.release();
+        // The released fork has the same history, so it is reused as is.
+        recorder.process();
+        let mut fork = recorder.fork().unwrap();
+        assert_eq!(builds.get(), 2);
+        assert_eq!(fork.state_hash(), recorder.state_hash());
+        // The messages of the last `process` call are still pending in both duels.
+        assert_eq!(fork.get_message(), recorder.get_message());
+        recorder.set_fork_cache_capacity(0);
+        fork.release();
+        recorder.fork().unwrap();
+        assert_eq!(builds.get(), 3);
+
+        let recorder = ReplayRecorder::new(DuelBuilder::new()).unwrap();
+        assert!(matches!(recorder.fork(), Err(DuelError::NotForkable)));
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::card::CardData;
+#[cfg(feature = "cdb")]
+use crate::cdb::CardDatabase;
+use crate::duel::CardHandler;
+#[cfg(feature = "cdb")]
+use crate::error::DuelError;
+
+/// The name reported by [`CardRepository::source_of`] for cards added with [`CardRepository::insert`].
+pub const CUSTOM_CARD_SOURCE: &str = "custom";
+
+/// A closure source, called without the repository locked, so it can look up other cards in it.
+pub trait CardSourceHandler: Fn(u32) -> Option<CardData> + Send + Sync + 'static {}
+impl<T: Fn(u32) -> Option<CardData> + Send + Sync + 'static> CardSourceHandler for T {}
+
+enum CardSource {
+    #[cfg(feature = "cdb")]
+    Database(CardDatabase),
+    Map(HashMap<u32, CardData>),
+    Handler(Box<dyn CardSourceHandler>),
+}
+
+impl CardSource {
+    fn get(&self, code: u32) -> Option<CardData> {
+        match self {
+            #[cfg(feature = "cdb")]
+            CardSource::Database(database) => database.get(code).cloned(),
+            CardSource::Map(map) => map.get(&code).cloned(),
+            CardSource::Handler(handler) => handler(code),
+        }
+    }
+}
+
+#[derive(Default)]
+struct CardRepositoryInner {
+    custom: HashMap<u32, CardData>,
+    sources: Vec<(Arc<str>, Arc<CardSource>)>,
+}
+
+/// Merges several card sources, like EDOPro's official, pre-release and unofficial databases.
+///
+/// Sources added later take priority over sources added earlier,
+/// and cards added with [`insert`](#method.insert) take priority over every source.
+///
+/// Cloning a repository returns a handle to the same sources,
+/// so cards can be added after [`card_handler`](#method.card_handler) has been given to a [`DuelBuilder`](crate::DuelBuilder).
+/// Note that ocgcore caches the data of every code it reads,
+/// so only codes the duel has not seen yet pick up changes.
+#[derive(Clone, Default)]
+pub struct CardRepository {
+    inner: Arc<Mutex<CardRepositoryInner>>,
+}
+
+impl CardRepository {
+    pub fn new() -> CardRepository {
+        CardRepository::default()
+    }
+    fn inner(&self) -> MutexGuard<'_, CardRepositoryInner> {
+        // A panicking card source cannot leave the sources in an inconsistent state.
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+    fn add_source(&self, name: &str, source: CardSource) {
+        self.inner().sources.push((name.into(), Arc::new(source)));
+    }
+    #[cfg(feature = "cdb")]
+    pub fn add_database(&self, name: &str, database: CardDatabase) {
+        self.add_source(name, CardSource::Database(database));
+    }
+    /// Opens a `.cdb` file and adds it as a source named after its path.
+    #[cfg(feature = "cdb")]
+    pub fn add_cdb<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), DuelError> {
+        let name = path.as_ref().display().to_string();
+        self.add_database(&name, CardDatabase::open(path)?);
+        Ok(())
+    }
+    pub fn add_map(&self, name: &str, cards: HashMap<u32, CardData>) {
+        self.add_source(name, CardSource::Map(cards));
+    }
+    /// Adds a closure as a source. Returning [`None`] falls through to lower priority sources.
+    pub fn add_handler<F: CardSourceHandler>(&self, name: &str, handler: F) {
+        self.add_source(name, CardSource::Handler(Box::new(handler)));
+    }
+    /// Adds or replaces a single card with the highest priority.
+    pub fn insert(&self, card: CardData) -> Option<CardData> {
+        self.inner().custom.insert(card.code, card)
+    }
+    /// Returns the card with the provided code and the name of the source it came from.
+    pub fn lookup(&self, code: u32) -> Option<(String, CardData)> {
+        let sources = {
+            let inner = self.inner();
+            if let Some(card) = inner.custom.get(&code) {
+                return Some((CUSTOM_CARD_SOURCE.to_owned(), card.clone()));
+            }
+            inner.sources.clone()
+        };
+        sources
+            .iter()
+            .rev()
+            .find_map(|(name, source)| Some((name.to_string(), source.get(code)?)))
+    }
+    pub fn get(&self, code: u32) -> Option<CardData> {
+        self.lookup(code).map(|(_, card)| card)
+    }
+    /// Returns the name of the source the card with the provided code would be read from.
+    pub fn source_of(&self, code: u32) -> Option<String> {
+        self.lookup(code).map(|(name, _)| name)
+    }
+    /// Returns a handler for [`DuelBuilder::set_card_handler`](crate::DuelBuilder::set_card_handler)
+    /// that shares this repository's sources.
+    /// Codes missing from every source are read as empty cards.
+    pub fn card_handler(&self) -> impl CardHandler {
+        let repository = self.clone();
+        move |code| {
+            repository.get(code).unwrap_or_else(|| CardData {
+                code,
+                ..Default::default()
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(code: u32, attack: i32) -> CardData {
+        CardData {
+            code,
+            attack,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_repository_priority() {
+        let repository = CardRepository::new();
+        repository.add_map(
+            "official",
+            HashMap::from([(1, card(1, 1000)), (2, card(2, 1000))]),
+        );
+        repository.add_handler("unofficial", |code| (code == 2).then(|| card(2, 2000)));
+        assert_eq!(repository.source_of(1).as_deref(), Some("official"));
+        assert_eq!(repository.source_of(2).as_deref(), Some("unofficial"));
+        assert_eq!(repository.get(2).unwrap().attack, 2000);
+        assert!(repository.get(3).is_none());
+    }
+    #[test]
+    fn test_repository_reentrant_handler() {
+        let repository = CardRepository::new();
+        repository.add_map("official", HashMap::from([(1, card(1, 1000))]));
+        let inner = repository.clone();
+        // Resolves an alias by looking up the original card in the same repository.
+        repository.add_handler("alias", move |code| {
+            (code == 2).then(|| CardData {
+                code,
+                ..inner.get(1).unwrap()
+            })
+        });
+        assert_eq!(repository.get(2).unwrap().attack, 1000);
+        assert!(repository.get(3).is_none());
+    }
+    #[test]
+    fn test_repository_hot_add() {
+        let repository = CardRepository::new();
+        let mut handler = repository.card_handler();
+        assert_eq!(handler(3).attack, 0);
+        repository.insert(card(3, 3000));
+        assert_eq!(handler(3).attack, 3000);
+        assert_eq!(repository.source_of(3).as_deref(), Some(CUSTOM_CARD_SOURCE));
+    }
+}
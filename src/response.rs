@@ -0,0 +1,254 @@
+/// Action chosen in reply to [`MSG_SELECT_IDLECMD`](crate::common::MSG_SELECT_IDLECMD).
+///
+/// Indices refer to the matching list of the [`SelectIdleCmd`](crate::SelectIdleCmd) prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdleCmdAction {
+    Summon(u32),
+    SpecialSummon(u32),
+    Reposition(u32),
+    MonsterSet(u32),
+    SpellSet(u32),
+    Activate(u32),
+    BattlePhase,
+    EndPhase,
+    Shuffle,
+}
+
+impl IdleCmdAction {
+    fn to_i32(self) -> i32 {
+        let (index, kind) = match self {
+            IdleCmdAction::Summon(index) => (index, 0),
+            IdleCmdAction::SpecialSummon(index) => (index, 1),
+            IdleCmdAction::Reposition(index) => (index, 2),
+            IdleCmdAction::MonsterSet(index) => (index, 3),
+            IdleCmdAction::SpellSet(index) => (index, 4),
+            IdleCmdAction::Activate(index) => (index, 5),
+            IdleCmdAction::BattlePhase => (0, 6),
+            IdleCmdAction::EndPhase => (0, 7),
+            IdleCmdAction::Shuffle => (0, 8),
+        };
+        ((index << 16) | kind) as i32
+    }
+}
+
+/// Action chosen in reply to [`MSG_SELECT_BATTLECMD`](crate::common::MSG_SELECT_BATTLECMD).
+///
+/// Indices refer to the matching list of the [`SelectBattleCmd`](crate::SelectBattleCmd) prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BattleCmdAction {
+    Activate(u32),
+    Attack(u32),
+    Main2,
+    EndPhase,
+}
+
+impl BattleCmdAction {
+    fn to_i32(self) -> i32 {
+        let (index, kind) = match self {
+            BattleCmdAction::Activate(index) => (index, 0),
+            BattleCmdAction::Attack(index) => (index, 1),
+            BattleCmdAction::Main2 => (0, 2),
+            BattleCmdAction::EndPhase => (0, 3),
+        };
+        ((index << 16) | kind) as i32
+    }
+}
+
+/// A single zone chosen in reply to [`MSG_SELECT_PLACE`](crate::common::MSG_SELECT_PLACE)
+/// or [`MSG_SELECT_DISFIELD`](crate::common::MSG_SELECT_DISFIELD).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Place {
+    pub con: u8,
+    /// Either [`LOCATION_MZONE`](crate::common::LOCATION_MZONE) or [`LOCATION_SZONE`](crate::common::LOCATION_SZONE).
+    pub loc: u32,
+    pub seq: u8,
+}
+
+/// A player response, serialized with [`to_bytes`](#method.to_bytes) into the layout
+/// ocgcore expects for the pending prompt.
+///
+/// Variants are named after the `MSG_*` prompt they answer.
+/// Card indices always refer to the order in which the prompt listed the cards.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Response {
+    SelectIdleCmd(IdleCmdAction),
+    SelectBattleCmd(BattleCmdAction),
+    SelectEffectYn(bool),
+    SelectYesNo(bool),
+    /// Index of the chosen option.
+    SelectOption(u32),
+    /// Indices of the chosen cards, or [`None`] to cancel.
+    SelectCard(Option<Vec<u32>>),
+    /// Indices of the chosen cards, or [`None`] to cancel.
+    SelectTribute(Option<Vec<u32>>),
+    /// Indices into [`SelectSum::selectable`](crate::SelectSum::selectable).
+    /// The cards in `must_select` are always included by the engine and must not be listed.
+    SelectSum(Vec<u32>),
+    /// Index of the card to toggle, or [`None`] to finish or cancel the selection.
+    /// Indices count through `selectable` first and then `unselectable`.
+    SelectUnselectCard(Option<u32>),
+    /// Index of the chain to activate, or [`None`] to pass.
+    SelectChain(Option<u32>),
+    SelectPlace(Vec<Place>),
+    SelectDisfield(Vec<Place>),
+    /// One of the `POS_*` constants.
+    SelectPosition(u8),
+    /// The new position of each card, or [`None`] to keep the default order.
+    SortCard(Option<Vec<u8>>),
+    /// The new position of each chain link, or [`None`] to keep the default order.
+    SortChain(Option<Vec<u8>>),
+    /// The number of counters to remove from each card.
+    SelectCounter(Vec<u16>),
+    /// The `RACE_*` value(s) to announce.
+    AnnounceRace(u64),
+    /// The `ATTRIBUTE_*` value(s) to announce.
+    AnnounceAttrib(u32),
+    /// The code of the card to announce.
+    AnnounceCard(u32),
+    /// Index of the number to announce.
+    AnnounceNumber(u32),
+    /// `1` for scissors, `2` for rock, `3` for paper.
+    RockPaperScissors(u8),
+}
+
+fn write_cards(buffer: &mut Vec<u8>, indices: &Option<Vec<u32>>) {
+    match indices {
+        None => buffer.extend_from_slice(&(-1i32).to_le_bytes()),
+        Some(indices) => {
+            // Type 0: a u32 count followed by u32 indices.
+            buffer.extend_from_slice(&0i32.to_le_bytes());
+            buffer.extend_from_slice(&(indices.len() as u32).to_le_bytes());
+            for index in indices {
+                buffer.extend_from_slice(&index.to_le_bytes());
+            }
+        }
+    }
+}
+
+fn write_places(buffer: &mut Vec<u8>, places: &[Place]) {
+    for place in places {
+        buffer.extend_from_slice(&[place.con, place.loc as u8, place.seq]);
+    }
+}
+
+fn write_sort(buffer: &mut Vec<u8>, order: &Option<Vec<u8>>) {
+    match order {
+        None => buffer.push(0xff),
+        Some(order) => buffer.extend_from_slice(order),
+    }
+}
+
+impl Response {
+    /// Serializes the response into the buffer expected by [`Duel::set_response`](crate::Duel::set_response).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        match self {
+            Response::SelectIdleCmd(action) => {
+                buffer.extend_from_slice(&action.to_i32().to_le_bytes())
+            }
+            Response::SelectBattleCmd(action) => {
+                buffer.extend_from_slice(&action.to_i32().to_le_bytes())
+            }
+            Response::SelectEffectYn(yes) | Response::SelectYesNo(yes) => {
+                buffer.extend_from_slice(&(*yes as i32).to_le_bytes())
+            }
+            Response::SelectOption(index) | Response::AnnounceNumber(index) => {
+                buffer.extend_from_slice(&index.to_le_bytes())
+            }
+            Response::SelectCard(indices) | Response::SelectTribute(indices) => {
+                write_cards(&mut buffer, indices)
+            }
+            Response::SelectSum(indices) => write_cards(&mut buffer, &Some(indices.clone())),
+            Response::SelectUnselectCard(index) => match index {
+                None => buffer.extend_from_slice(&(-1i32).to_le_bytes()),
+                Some(index) => {
+                    buffer.extend_from_slice(&1i32.to_le_bytes());
+                    buffer.extend_from_slice(&index.to_le_bytes());
+                }
+            },
+            Response::SelectChain(index) => {
+                let index = index.map_or(-1, |index| index as i32);
+                buffer.extend_from_slice(&index.to_le_bytes())
+            }
+            Response::SelectPlace(places) | Response::SelectDisfield(places) => {
+                write_places(&mut buffer, places)
+            }
+            Response::SelectPosition(pos) => buffer.extend_from_slice(&(*pos as i32).to_le_bytes()),
+            Response::SortCard(order) | Response::SortChain(order) => {
+                write_sort(&mut buffer, order)
+            }
+            Response::SelectCounter(counts) => {
+                for count in counts {
+                    buffer.extend_from_slice(&count.to_le_bytes());
+                }
+            }
+            Response::AnnounceRace(race) => buffer.extend_from_slice(&race.to_le_bytes()),
+            Response::AnnounceAttrib(attribute) => {
+                buffer.extend_from_slice(&attribute.to_le_bytes())
+            }
+            Response::AnnounceCard(code) => buffer.extend_from_slice(&code.to_le_bytes()),
+            Response::RockPaperScissors(hand) => {
+                buffer.extend_from_slice(&(*hand as i32).to_le_bytes())
+            }
+        }
+        buffer
+    }
+}
+
+impl From<&Response> for Vec<u8> {
+    fn from(val: &Response) -> Self {
+        val.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::*;
+
+    #[test]
+    fn test_command_responses() {
+        assert_eq!(
+            Response::SelectIdleCmd(IdleCmdAction::Activate(2)).to_bytes(),
+            ((2 << 16) | 5i32).to_le_bytes()
+        );
+        assert_eq!(
+            Response::SelectIdleCmd(IdleCmdAction::EndPhase).to_bytes(),
+            7i32.to_le_bytes()
+        );
+        assert_eq!(
+            Response::SelectBattleCmd(BattleCmdAction::Attack(1)).to_bytes(),
+            ((1 << 16) | 1i32).to_le_bytes()
+        );
+        assert_eq!(
+            Response::SelectChain(None).to_bytes(),
+            (-1i32).to_le_bytes()
+        );
+    }
+    #[test]
+    fn test_card_responses() {
+        assert_eq!(
+            Response::SelectCard(Some(vec![0, 3])).to_bytes(),
+            [0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0]
+        );
+        assert_eq!(
+            Response::SelectTribute(None).to_bytes(),
+            (-1i32).to_le_bytes()
+        );
+        assert_eq!(
+            Response::SelectUnselectCard(Some(4)).to_bytes(),
+            [1, 0, 0, 0, 4, 0, 0, 0]
+        );
+        assert_eq!(Response::SortCard(None).to_bytes(), [0xff]);
+        assert_eq!(Response::SelectCounter(vec![1, 2]).to_bytes(), [1, 0, 2, 0]);
+        assert_eq!(
+            Response::SelectPlace(vec![Place {
+                con: 1,
+                loc: LOCATION_SZONE,
+                seq: 4,
+            }])
+            .to_bytes(),
+            [1, LOCATION_SZONE as u8, 4]
+        );
+    }
+}
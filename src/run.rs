@@ -0,0 +1,183 @@
+use crate::buffer::BufferReader;
+use crate::duel::{Duel, DuelStatus};
+use crate::error::DuelError;
+use crate::message::Message;
+
+/// How many times [`Duel::run_until_input`] calls [`Duel::process`] before giving up.
+pub const DEFAULT_ITERATION_BUDGET: usize = 100_000;
+
+/// The winner announced by `MSG_WIN`.
+/// `player` is 2 when the duel ended in a draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DuelResult {
+    pub player: u8,
+    pub reason: u8,
+}
+
+impl DuelResult {
+    /// Returns the result announced by the last `MSG_WIN` of the messages.
+    pub fn find(messages: &[Message]) -> Option<DuelResult> {
+        messages.iter().rev().find_map(|message| match message {
+            Message::Win { player, reason } => Some(DuelResult {
+                player: *player,
+                reason: *reason,
+            }),
+            _ => None,
+        })
+    }
+}
+
+/// Why [`Duel::run_until_input`] stopped processing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RunOutcome {
+    /// The duel is waiting for a response to this prompt.
+    Awaiting(Message),
+    /// The last response was rejected with `MSG_RETRY`.
+    /// The engine does not send the prompt again, so the previous prompt must be answered again.
+    Retry,
+    /// The duel ended, with the result if the engine announced one.
+    Ended(Option<DuelResult>),
+}
+
+/// Everything produced by one call to [`Duel::run_until_input`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DuelRun {
+    /// Every message produced, including the prompt or `MSG_WIN` found in `outcome`.
+    pub messages: Vec<Message>,
+    pub outcome: RunOutcome,
+}
+
+impl Duel {
+    /// Processes the duel until it needs a response or ends,
+    /// decoding the messages produced along the way.
+    /// Gives up after [`DEFAULT_ITERATION_BUDGET`] calls to [`process`](#method.process).
+    ///
+    /// See [`run_until_input_with_budget`](#method.run_until_input_with_budget).
+    pub fn run_until_input(&self) -> Result<DuelRun, DuelError> {
+        self.run_until_input_with_budget(DEFAULT_ITERATION_BUDGET)
+    }
+    /// Processes the duel until it needs a response or ends,
+    /// calling [`process`](#method.process) at most `budget` times,
+    /// so a script that never stops returning [`DuelStatus::Continue`] cannot hang the caller.
+    ///
+    /// Returns [`DuelError::IterationBudgetExceeded`] once the budget is spent.
+    /// A message that could not be decoded is kept as [`Message::Malformed`],
+    /// so the messages around it are not lost.
+    pub fn run_until_input_with_budget(&self, budget: usize) -> Result<DuelRun, DuelError> {
+        let mut messages = Vec::new();
+        for _ in 0..budget {
+            let status = self.process();
+            decode_messages(&self.get_message(), &mut messages);
+            let outcome = match status {
+                DuelStatus::Continue => continue,
+                // The engine only waits after sending a prompt or `MSG_RETRY`.
+                DuelStatus::Awaiting => messages
+                    .iter()
+                    .rev()
+                    .find(|message| message.prompt_player().is_some())
+                    .filter(|_| messages.last() != Some(&Message::Retry))
+                    .map_or(RunOutcome::Retry, |prompt| {
+                        RunOutcome::Awaiting(prompt.clone())
+                    }),
+                DuelStatus::End => RunOutcome::Ended(DuelResult::find(&messages)),
+            };
+            return Ok(DuelRun { messages, outcome });
+        }
+        Err(DuelError::IterationBudgetExceeded(budget))
+    }
+}
+
+/// Decodes every record of a [`Duel::get_message`] buffer with [`decode_message`].
+/// Trailing bytes too short to hold the length of a record are dropped.
+pub(crate) fn decode_messages(buffer: &[u8], messages: &mut Vec<Message>) {
+    let mut reader = BufferReader::new(buffer);
+    while let Ok(len) = reader.read_u32() {
+        let record = reader
+            .read_bytes(len as usize)
+            .unwrap_or_else(|_| reader.rest());
+        messages.extend(decode_message(record));
+    }
+}
+
+/// Decodes a record starting with its message id,
+/// keeping it as [`Message::Malformed`] if it fails to decode, for example because it is truncated.
+/// Returns [`None`] for an empty record.
+pub(crate) fn decode_message(record: &[u8]) -> Option<Message> {
+    let (&id, bytes) = record.split_first()?;
+    Some(
+        Message::parse(record).unwrap_or_else(|_| Message::Malformed {
+            id,
+            bytes: bytes.to_vec(),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{MSG_NEW_TURN, MSG_WIN};
+    use crate::duel::DuelBuilder;
+
+    #[test]
+    fn test_run_until_input() {
+        let duel = DuelBuilder::new().build();
+        duel.start();
+        assert!(matches!(
+            duel.run_until_input_with_budget(0),
+            Err(DuelError::IterationBudgetExceeded(0))
+        ));
+        // Without cards, the first player is asked what to do in their main phase.
+        let run = duel.run_until_input().unwrap();
+        let RunOutcome::Awaiting(prompt) = &run.outcome else {
+            panic!("unexpected outcome {:?}", run.outcome);
+        };
+        assert!(matches!(prompt, Message::SelectIdleCmd(_)));
+        assert_eq!(prompt.prompt_player(), Some(0));
+        assert!(run.messages.contains(prompt));
+    }
+    #[test]
+    fn test_decode_messages() {
+        let mut buffer = Vec::new();
+        for body in [
+            &[MSG_NEW_TURN, 1][..],
+            &[MSG_WIN, 0],
+            &[200, 7],
+            &[MSG_WIN, 1, 4],
+        ] {
+            buffer.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(body);
+        }
+        let mut messages = Vec::new();
+        // A fragment too short to be a length is dropped.
+        decode_messages(&[&buffer[..], &[2, 0]].concat(), &mut messages);
+        // The last record is truncated.
+        decode_messages(
+            &[&buffer[..], &[3, 0, 0, 0, MSG_WIN]].concat(),
+            &mut messages,
+        );
+        let expected = [
+            Message::NewTurn { player: 1 },
+            Message::Malformed {
+                id: MSG_WIN,
+                bytes: vec![0],
+            },
+            Message::Unknown {
+                id: 200,
+                bytes: vec![7],
+            },
+            Message::Win {
+                player: 1,
+                reason: 4,
+            },
+        ];
+        assert_eq!(messages[..4], expected[..]);
+        assert_eq!(messages[4..8], expected[..]);
+        assert_eq!(
+            messages[8..],
+            [Message::Malformed {
+                id: MSG_WIN,
+                bytes: vec![]
+            }]
+        );
+    }
+}
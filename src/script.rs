@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::duel::{Duel, ScriptHandler};
+use crate::error::DuelError;
+
+/// Somewhere Lua scripts can be read from, such as a [`ScriptDirectory`], a [`ScriptBundle`] or a [`ScriptChain`] of them.
+pub trait ScriptSource {
+    /// Reads the script with the provided name,
+    /// or returns [`None`] if this source does not contain it.
+    fn read_script(&mut self, name: &str) -> Result<Option<Arc<str>>, DuelError>;
+    /// Reads the script with the provided name,
+    /// returning [`DuelError::ScriptNotFound`] if this source does not contain it.
+    fn read(&mut self, name: &str) -> Result<Arc<str>, DuelError> {
+        self.read_script(name)?
+            .ok_or_else(|| DuelError::ScriptNotFound(name.to_owned()))
+    }
+    /// Reads the script with the provided name and loads it into the duel.
+    ///
+    /// Returns [`DuelError::ScriptNotFound`] if this source does not contain the script,
+    /// and [`DuelError::ScriptLoadFailure`] if the engine failed to compile or run it.
+    fn load(&mut self, duel: &Duel, name: &str) -> Result<(), DuelError> {
+        let script = self.read(name)?;
+        duel.load_script(&script, name)
+    }
+    /// Converts the source into a handler for [`DuelBuilder::set_script_handler`](crate::DuelBuilder::set_script_handler).
+    /// The errors returned by the handler are [`DuelError`]s.
+    fn into_script_handler(mut self) -> impl ScriptHandler
+    where
+        Self: Sized + 'static,
+    {
+        move |duel: &Duel, name: &str| Ok(self.load(duel, name)?)
+    }
+}
+
+/// Loads scripts from an ordered list of directories,
+/// like EDOPro's `script/official`, `script/unofficial` and `script` folders.
+///
+/// Directories are searched in the order they were added and the first file found is used.
+/// File contents are cached, so scripts requested more than once are only read from disk once.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptDirectory {
+    directories: Vec<PathBuf>,
+    cache: HashMap<String, Arc<str>>,
+}
+
+impl ScriptDirectory {
+    pub fn new<P, I>(directories: I) -> ScriptDirectory
+    where
+        P: Into<PathBuf>,
+        I: IntoIterator<Item = P>,
+    {
+        ScriptDirectory {
+            directories: directories.into_iter().map(Into::into).collect(),
+            cache: HashMap::new(),
+        }
+    }
+    /// Adds a directory with a lower priority than every directory added before it.
+    pub fn add_directory<P: Into<PathBuf>>(&mut self, directory: P) {
+        self.directories.push(directory.into());
+    }
+    pub fn directories(&self) -> &[PathBuf] {
+        &self.directories
+    }
+    /// Returns the path the script with the provided name would be read from.
+    /// Names that could escape the directories, such as absolute paths or paths containing `..`, are never found.
+    pub fn find(&self, name: &str) -> Option<PathBuf> {
+        if !is_relative_name(name) {
+            return None;
+        }
+        self.directories
+            .iter()
+            .map(|directory| directory.join(name))
+            .find(|path| path.is_file())
+    }
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+}
+
+impl ScriptSource for ScriptDirectory {
+    fn read_script(&mut self, name: &str) -> Result<Option<Arc<str>>, DuelError> {
+        if let Some(script) = self.cache.get(name) {
+            return Ok(Some(script.clone()));
+        }
+        if !is_relative_name(name) {
+            return Err(DuelError::InvalidScriptName(name.to_owned()));
+        }
+        for directory in &self.directories {
+            let script: Arc<str> = match fs::read(directory.join(name)) {
+                Ok(bytes) => String::from_utf8_lossy(&bytes).into(),
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(source) => {
+                    return Err(DuelError::ScriptRead {
+                        name: name.to_owned(),
+                        source,
+                    })
+                }
+            };
+            self.cache.insert(name.to_owned(), script.clone());
+            return Ok(Some(script));
+        }
+        Ok(None)
+    }
+}
+
+/// Whether joining the name to a directory stays inside that directory.
+fn is_relative_name(name: &str) -> bool {
+    !name.is_empty()
+        && Path::new(name)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Scripts kept in memory, for example embedded into the binary with [`include_str!`] or [`include_bytes!`].
+#[derive(Debug, Clone, Default)]
+pub struct ScriptBundle {
+    scripts: HashMap<String, Arc<str>>,
+}
+
+impl ScriptBundle {
+    pub fn new() -> ScriptBundle {
+        ScriptBundle::default()
+    }
+    pub fn insert(&mut self, name: &str, script: &str) -> Option<Arc<str>> {
+        self.scripts.insert(name.to_owned(), script.into())
+    }
+    /// Inserts a script that is not known to be UTF-8, replacing invalid sequences.
+    pub fn insert_bytes(&mut self, name: &str, script: &[u8]) -> Option<Arc<str>> {
+        self.scripts
+            .insert(name.to_owned(), String::from_utf8_lossy(script).into())
+    }
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.scripts.get(name).map(|script| &**script)
+    }
+    pub fn len(&self) -> usize {
+        self.scripts.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+}
+
+impl<'a, S: AsRef<[u8]>> FromIterator<(&'a str, S)> for ScriptBundle {
+    fn from_iter<I: IntoIterator<Item = (&'a str, S)>>(iter: I) -> ScriptBundle {
+        let mut bundle = ScriptBundle::new();
+        for (name, script) in iter {
+            bundle.insert_bytes(name, script.as_ref());
+        }
+        bundle
+    }
+}
+
+impl ScriptSource for ScriptBundle {
+    fn read_script(&mut self, name: &str) -> Result<Option<Arc<str>>, DuelError> {
+        Ok(self.scripts.get(name).cloned())
+    }
+}
+
+/// Scripts read from a zip archive, such as an EDOPro expansion.
+///
+/// Entries are looked up by file name, ignoring the directories inside the archive,
+/// so `script/c1.lua` is found when the engine requests `c1.lua`.
+/// If several entries share a file name the first one is used.
+#[cfg(feature = "zip")]
+#[derive(Debug)]
+pub struct ScriptArchive<R> {
+    archive: zip::ZipArchive<R>,
+    entries: HashMap<String, usize>,
+    cache: HashMap<String, Arc<str>>,
+}
+
+#[cfg(feature = "zip")]
+impl ScriptArchive<std::io::BufReader<fs::File>> {
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, DuelError> {
+        let name = path.as_ref().display().to_string();
+        let file = fs::File::open(path).map_err(|source| DuelError::ScriptRead { name, source })?;
+        ScriptArchive::new(std::io::BufReader::new(file))
+    }
+}
+
+#[cfg(feature = "zip")]
+impl<R: std::io::Read + std::io::Seek> ScriptArchive<R> {
+    pub fn new(reader: R) -> Result<ScriptArchive<R>, DuelError> {
+        let mut archive = zip::ZipArchive::new(reader)?;
+        let mut entries = HashMap::new();
+        for index in 0..archive.len() {
+            let entry = archive.by_index_raw(index)?;
+            if let Some(name) = entry
+                .name()
+                .rsplit('/')
+                .next()
+                .filter(|name| !name.is_empty())
+            {
+                entries.entry(name.to_owned()).or_insert(index);
+            }
+        }
+        Ok(ScriptArchive {
+            archive,
+            entries,
+            cache: HashMap::new(),
+        })
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(feature = "zip")]
+impl<R: std::io::Read + std::io::Seek> ScriptSource for ScriptArchive<R> {
+    fn read_script(&mut self, name: &str) -> Result<Option<Arc<str>>, DuelError> {
+        use std::io::Read;
+
+        if let Some(script) = self.cache.get(name) {
+            return Ok(Some(script.clone()));
+        }
+        let Some(&index) = self.entries.get(name) else {
+            return Ok(None);
+        };
+        let mut bytes = Vec::new();
+        self.archive
+            .by_index(index)?
+            .read_to_end(&mut bytes)
+            .map_err(|source| DuelError::ScriptRead {
+                name: name.to_owned(),
+                source,
+            })?;
+        let script: Arc<str> = String::from_utf8_lossy(&bytes).into();
+        self.cache.insert(name.to_owned(), script.clone());
+        Ok(Some(script))
+    }
+}
+
+/// Several script sources searched in the order they were added, the first source containing a script winning.
+#[derive(Default)]
+pub struct ScriptChain {
+    sources: Vec<Box<dyn ScriptSource + Send>>,
+}
+
+impl ScriptChain {
+    pub fn new() -> ScriptChain {
+        ScriptChain::default()
+    }
+    /// Adds a source with a lower priority than every source added before it.
+    pub fn push<S: ScriptSource + Send + 'static>(&mut self, source: S) {
+        self.sources.push(Box::new(source));
+    }
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+}
+
+impl ScriptSource for ScriptChain {
+    fn read_script(&mut self, name: &str) -> Result<Option<Arc<str>>, DuelError> {
+        for source in &mut self.sources {
+            if let Some(script) = source.read_script(name)? {
+                return Ok(Some(script));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_directory_priority() {
+        let root = std::env::temp_dir().join(format!("ygopro-script-dir-{}", std::process::id()));
+        let official = root.join("official");
+        fs::create_dir_all(&official).unwrap();
+        fs::write(official.join("c1.lua"), "-- official").unwrap();
+        fs::write(root.join("c1.lua"), "-- fallback").unwrap();
+        fs::write(root.join("constant.lua"), "CARD_CYBER_DRAGON = 70095154").unwrap();
+        let mut scripts = ScriptDirectory::new([&official, &root]);
+        assert_eq!(&*scripts.read("c1.lua").unwrap(), "-- official");
+        assert_eq!(
+            scripts.find("constant.lua"),
+            Some(root.join("constant.lua"))
+        );
+        assert!(scripts.read("constant.lua").is_ok());
+        // Cached scripts are still returned after the file is gone.
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(&*scripts.read("c1.lua").unwrap(), "-- official");
+        assert!(matches!(
+            scripts.read("c2.lua"),
+            Err(DuelError::ScriptNotFound(name)) if name == "c2.lua"
+        ));
+        for name in ["../c1.lua", "/etc/passwd", "official/../../c1.lua"] {
+            assert!(scripts.find(name).is_none());
+            assert!(matches!(
+                scripts.read(name),
+                Err(DuelError::InvalidScriptName(_))
+            ));
+        }
+    }
+    #[test]
+    fn test_script_chain() {
+        let mut chain = ScriptChain::new();
+        chain.push(ScriptBundle::from_iter([(
+            "constant.lua",
+            b"CARD_CYBER_DRAGON = 70095154".as_slice(),
+        )]));
+        chain.push(ScriptBundle::from_iter([
+            ("constant.lua", "-- shadowed"),
+            ("utility.lua", "-- utility"),
+        ]));
+        assert_eq!(
+            &*chain.read("constant.lua").unwrap(),
+            "CARD_CYBER_DRAGON = 70095154"
+        );
+        assert_eq!(&*chain.read("utility.lua").unwrap(), "-- utility");
+        assert!(chain.read_script("c1.lua").unwrap().is_none());
+    }
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_script_archive() {
+        use std::io::{Cursor, Write};
+
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        writer.start_file("script/c1.lua", options).unwrap();
+        writer.write_all(b"-- c1").unwrap();
+        writer.start_file("pics/1.jpg", options).unwrap();
+        let buffer = writer.finish().unwrap();
+        let mut archive = ScriptArchive::new(buffer).unwrap();
+        assert_eq!(archive.len(), 2);
+        assert_eq!(&*archive.read("c1.lua").unwrap(), "-- c1");
+        assert!(archive.read_script("c2.lua").unwrap().is_none());
+    }
+}
@@ -0,0 +1,95 @@
+use crate::common::*;
+use crate::field::FieldSnapshot;
+use crate::query::CardQuery;
+
+/// The `QUERY_*` flags used by [`Duel::snapshot`](crate::Duel::snapshot).
+pub const SNAPSHOT_QUERY_FLAGS: u32 = QUERY_CODE
+    | QUERY_POSITION
+    | QUERY_ALIAS
+    | QUERY_TYPE
+    | QUERY_LEVEL
+    | QUERY_RANK
+    | QUERY_ATTRIBUTE
+    | QUERY_RACE
+    | QUERY_ATTACK
+    | QUERY_DEFENSE
+    | QUERY_BASE_ATTACK
+    | QUERY_BASE_DEFENSE
+    | QUERY_REASON
+    | QUERY_REASON_CARD
+    | QUERY_EQUIP_CARD
+    | QUERY_TARGET_CARD
+    | QUERY_OVERLAY_CARD
+    | QUERY_COUNTERS
+    | QUERY_OWNER
+    | QUERY_STATUS
+    | QUERY_IS_PUBLIC
+    | QUERY_LSCALE
+    | QUERY_RSCALE
+    | QUERY_LINK
+    | QUERY_IS_HIDDEN
+    | QUERY_COVER;
+
+/// The locations queried for each player by [`Duel::snapshot`](crate::Duel::snapshot).
+pub const SNAPSHOT_LOCATIONS: [u32; 7] = [
+    LOCATION_DECK,
+    LOCATION_HAND,
+    LOCATION_MZONE,
+    LOCATION_SZONE,
+    LOCATION_GRAVE,
+    LOCATION_REMOVED,
+    LOCATION_EXTRA,
+];
+
+/// Every card one player controls, one entry per slot.
+/// Zones are [`None`] when empty; piles never contain [`None`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlayerSnapshot {
+    pub deck: Vec<Option<CardQuery>>,
+    pub hand: Vec<Option<CardQuery>>,
+    pub monster_zones: Vec<Option<CardQuery>>,
+    pub spell_zones: Vec<Option<CardQuery>>,
+    pub grave: Vec<Option<CardQuery>>,
+    pub removed: Vec<Option<CardQuery>>,
+    pub extra: Vec<Option<CardQuery>>,
+}
+
+impl PlayerSnapshot {
+    /// Returns the cards queried from one of the [`SNAPSHOT_LOCATIONS`].
+    pub fn location(&self, loc: u32) -> Option<&Vec<Option<CardQuery>>> {
+        match loc {
+            LOCATION_DECK => Some(&self.deck),
+            LOCATION_HAND => Some(&self.hand),
+            LOCATION_MZONE => Some(&self.monster_zones),
+            LOCATION_SZONE => Some(&self.spell_zones),
+            LOCATION_GRAVE => Some(&self.grave),
+            LOCATION_REMOVED => Some(&self.removed),
+            LOCATION_EXTRA => Some(&self.extra),
+            _ => None,
+        }
+    }
+    pub(crate) fn location_mut(&mut self, loc: u32) -> Option<&mut Vec<Option<CardQuery>>> {
+        match loc {
+            LOCATION_DECK => Some(&mut self.deck),
+            LOCATION_HAND => Some(&mut self.hand),
+            LOCATION_MZONE => Some(&mut self.monster_zones),
+            LOCATION_SZONE => Some(&mut self.spell_zones),
+            LOCATION_GRAVE => Some(&mut self.grave),
+            LOCATION_REMOVED => Some(&mut self.removed),
+            LOCATION_EXTRA => Some(&mut self.extra),
+            _ => None,
+        }
+    }
+}
+
+/// Everything the engine knows about a duel at one point in time,
+/// as returned by [`Duel::snapshot`](crate::Duel::snapshot).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DuelSnapshot {
+    /// The `QUERY_*` flags the cards were queried with.
+    pub flags: u32,
+    pub field: FieldSnapshot,
+    pub players: [PlayerSnapshot; 2],
+}
@@ -0,0 +1,265 @@
+use std::ops::Deref;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::card::NewCardInfo;
+use crate::duel::{
+    CardHandler, CardReadDoneHandler, Duel, DuelBuilder, DuelStatus, LogHandler, ScriptHandler,
+};
+use crate::error::DuelError;
+use crate::player::Player;
+use crate::query::QueryInfo;
+use crate::response::Response;
+use crate::snapshot::DuelSnapshot;
+
+pub trait SendCardHandler: CardHandler + Send {}
+impl<T: CardHandler + Send> SendCardHandler for T {}
+pub trait SendScriptHandler: ScriptHandler + Send {}
+impl<T: ScriptHandler + Send> SendScriptHandler for T {}
+pub trait SendLogHandler: LogHandler + Send {}
+impl<T: LogHandler + Send> SendLogHandler for T {}
+pub trait SendCardReadDoneHandler: CardReadDoneHandler + Send {}
+impl<T: CardReadDoneHandler + Send> SendCardReadDoneHandler for T {}
+
+/// A [`DuelBuilder`] that only accepts handlers that are [`Send`], so the duel it builds can be moved between threads.
+#[derive(Default)]
+pub struct SendDuelBuilder {
+    builder: DuelBuilder,
+}
+
+// SAFETY: The handlers of the inner builder are either the defaults, which capture nothing,
+// or were required to be `Send` when they were set.
+unsafe impl Send for SendDuelBuilder {}
+
+impl SendDuelBuilder {
+    pub fn new() -> SendDuelBuilder {
+        SendDuelBuilder::default()
+    }
+    /// See [`DuelBuilder::set_card_handler`].
+    pub fn set_card_handler<F: SendCardHandler>(&mut self, callback: F) {
+        self.builder.set_card_handler(callback);
+    }
+    /// See [`DuelBuilder::set_script_handler`].
+    pub fn set_script_handler<F: SendScriptHandler>(&mut self, callback: F) {
+        self.builder.set_script_handler(callback);
+    }
+    /// See [`DuelBuilder::set_log_handler`].
+    pub fn set_log_handler<F: SendLogHandler>(&mut self, callback: F) {
+        self.builder.set_log_handler(callback);
+    }
+    /// See [`DuelBuilder::set_card_read_done_handler`].
+    pub fn set_card_read_done_handler<F: SendCardReadDoneHandler>(&mut self, callback: F) {
+        self.builder.set_card_read_done_handler(callback);
+    }
+    pub fn add_startup_script(&mut self, name: &str) {
+        self.builder.add_startup_script(name);
+    }
+    pub fn set_startup_scripts<S, I>(&mut self, names: I)
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = S>,
+    {
+        self.builder.set_startup_scripts(names);
+    }
+    pub fn use_edopro_startup_scripts(&mut self) {
+        self.builder.use_edopro_startup_scripts();
+    }
+    pub fn set_seed(&mut self, seed: [u64; 4]) {
+        self.builder.set_seed(seed);
+    }
+    pub fn set_flags(&mut self, flags: u64) {
+        self.builder.set_flags(flags);
+    }
+    pub fn set_team_1(&mut self, player: Player) {
+        self.builder.set_team_1(player);
+    }
+    pub fn set_team_2(&mut self, player: Player) {
+        self.builder.set_team_2(player);
+    }
+    pub fn set_enable_unsafe_libraries(&mut self, enable: bool) {
+        self.builder.set_enable_unsafe_libraries(enable);
+    }
+    /// See [`DuelBuilder::build`].
+    pub fn build(self) -> SendDuel {
+        SendDuel {
+            duel: self.builder.build(),
+        }
+    }
+    /// See [`DuelBuilder::try_build`].
+    pub fn try_build(self) -> Result<SendDuel, DuelError> {
+        Ok(SendDuel {
+            duel: self.builder.try_build()?,
+        })
+    }
+}
+
+/// A [`Duel`] built by a [`SendDuelBuilder`], which can be moved to another thread.
+///
+/// It is not [`Sync`]: the engine must only be used from one thread at a time.
+#[derive(Debug)]
+pub struct SendDuel {
+    duel: Pin<Box<Duel>>,
+}
+
+// SAFETY: Every handler of the duel is `Send`.
+// The engine instance is only reachable through the duel, so they always move together,
+// and the panic slot shared between the duel and its handlers is an `Arc<Mutex<_>>`.
+unsafe impl Send for SendDuel {}
+
+impl Deref for SendDuel {
+    type Target = Duel;
+    fn deref(&self) -> &Duel {
+        &self.duel
+    }
+}
+
+type Job = Box<dyn FnOnce(&Duel) + Send>;
+
+/// Owns a [`SendDuel`] on a dedicated thread, so servers can run many duels concurrently.
+///
+/// Every call is sent to the thread over a channel.
+/// The blocking methods wait for the result,
+/// while [`submit`](#method.submit) returns a [`Receiver`] for it.
+/// Dropping the worker stops its thread once the calls already sent have run.
+#[derive(Debug)]
+pub struct DuelWorker {
+    sender: Option<Sender<Job>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DuelWorker {
+    pub fn new(duel: SendDuel) -> DuelWorker {
+        let (sender, receiver) = channel::<Job>();
+        let thread = thread::Builder::new()
+            .name("duel-worker".to_owned())
+            .spawn(move || {
+                for job in receiver {
+                    job(&duel);
+                }
+            })
+            .expect("failed to spawn duel worker thread");
+        DuelWorker {
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+    /// Builds the duel and moves it to a new worker thread.
+    pub fn spawn(builder: SendDuelBuilder) -> Result<DuelWorker, DuelError> {
+        Ok(DuelWorker::new(builder.try_build()?))
+    }
+    /// Runs `f` with the duel on the worker thread without waiting for it.
+    /// The receiver gets the result, or the panic if `f` panicked.
+    pub fn submit<R, F>(&self, f: F) -> Receiver<thread::Result<R>>
+    where
+        R: Send + 'static,
+        F: FnOnce(&Duel) -> R + Send + 'static,
+    {
+        let (sender, receiver) = channel();
+        let job: Job = Box::new(move |duel| {
+            let _ = sender.send(catch_unwind(AssertUnwindSafe(|| f(duel))));
+        });
+        // The thread only stops once the sender is dropped, so this cannot fail.
+        let _ = self.sender.as_ref().unwrap().send(job);
+        receiver
+    }
+    /// Runs `f` with the duel on the worker thread and waits for its result.
+    ///
+    /// # Panics
+    /// Resumes the panic if `f` panicked.
+    pub fn run<R, F>(&self, f: F) -> R
+    where
+        R: Send + 'static,
+        F: FnOnce(&Duel) -> R + Send + 'static,
+    {
+        match self.submit(f).recv() {
+            Ok(Ok(result)) => result,
+            Ok(Err(panic)) => resume_unwind(panic),
+            Err(_) => panic!("the duel worker thread stopped"),
+        }
+    }
+    /// See [`Duel::new_card`].
+    pub fn new_card(&self, info: NewCardInfo) {
+        self.run(move |duel| duel.new_card(info))
+    }
+    /// See [`Duel::start`].
+    pub fn start(&self) {
+        self.run(|duel| duel.start())
+    }
+    /// See [`Duel::process`].
+    pub fn process(&self) -> DuelStatus {
+        self.run(|duel| duel.process())
+    }
+    /// See [`Duel::get_message`].
+    pub fn get_message(&self) -> Vec<u8> {
+        self.run(|duel| duel.get_message())
+    }
+    /// See [`Duel::set_response`].
+    pub fn set_response(&self, response: Vec<u8>) {
+        self.run(move |duel| duel.set_response(&response))
+    }
+    /// See [`Duel::respond`].
+    pub fn respond(&self, response: Response) {
+        self.run(move |duel| duel.respond(&response))
+    }
+    /// See [`Duel::load_script`].
+    pub fn load_script(&self, src_code: String, name: String) -> Result<(), DuelError> {
+        self.run(move |duel| duel.load_script(&src_code, &name))
+    }
+    /// See [`Duel::query_count`].
+    pub fn query_count(&self, team: u8, loc: u32) -> u32 {
+        self.run(move |duel| duel.query_count(team, loc))
+    }
+    /// See [`Duel::query`].
+    pub fn query(&self, query_info: QueryInfo) -> Option<Vec<u8>> {
+        self.run(move |duel| duel.query(query_info))
+    }
+    /// See [`Duel::query_location`].
+    pub fn query_location(&self, query_info: QueryInfo) -> Option<Vec<u8>> {
+        self.run(move |duel| duel.query_location(query_info))
+    }
+    /// See [`Duel::query_field`].
+    pub fn query_field(&self) -> Option<Vec<u8>> {
+        self.run(|duel| duel.query_field())
+    }
+    /// See [`Duel::snapshot`].
+    pub fn snapshot(&self) -> Result<DuelSnapshot, DuelError> {
+        self.run(|duel| duel.snapshot())
+    }
+}
+
+impl Drop for DuelWorker {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::{ScriptBundle, ScriptSource};
+
+    #[test]
+    fn test_duel_worker() {
+        let mut builder = SendDuelBuilder::new();
+        let scripts = ScriptBundle::from_iter([("constant.lua", "CARD_CYBER_DRAGON = 70095154")]);
+        builder.set_script_handler(scripts.into_script_handler());
+        builder.add_startup_script("constant.lua");
+        let worker = DuelWorker::spawn(builder).unwrap();
+        let thread_name = worker.run(|_| thread::current().name().map(str::to_owned));
+        assert_eq!(thread_name.as_deref(), Some("duel-worker"));
+        worker.start();
+        worker.process();
+        assert!(worker
+            .load_script("invalid script".into(), "c1.lua".into())
+            .is_err());
+        let panic = catch_unwind(AssertUnwindSafe(|| worker.run(|_| panic!("job panicked"))));
+        assert!(panic.is_err());
+        // The worker keeps running after a job panicked.
+        assert_eq!(worker.query_count(0, crate::common::LOCATION_DECK), 0);
+    }
+}